@@ -1,30 +1,142 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 
+mod build;
+mod controlplane;
+mod delete;
+mod doctor;
+mod health;
+mod init;
+mod inspect;
+mod list;
+mod logs;
+mod prompt;
+mod pull;
 mod push;
+mod registry;
+mod status;
+mod transport;
 mod trigger;
+mod validate;
+mod worker;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    /// Increase logging verbosity (-v debug, -vv trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
 
+    /// Only log errors and print nothing on success, except command results such as
+    /// listings or requested JSON output
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Log output format (defaults to $NOCTI_LOG_FORMAT or text)
+    #[arg(long, value_enum, global = true)]
+    pub log_format: Option<LogFormat>,
+
     #[command(subcommand)]
     command: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Command {
-    Trigger {
-        action: String,
-        payload: String,
-        #[arg(value_name = "KEY=VALUE", trailing_var_arg = true)]
-        metadata: Vec<String>,
+    Trigger(trigger::TriggerArgs),
+    Push(push::PushArgs),
+    /// Download an artifact by project name or digest
+    Pull(pull::PullArgs),
+    /// Build the project locally and write the archive to a file
+    Build {
+        path: String,
+        /// Config file to use instead of <PATH>/Nocti.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// Path of the tar archive to write
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+    },
+    /// Check Nocti.toml and resolve the build target without building
+    Validate {
+        #[arg(default_value = ".")]
+        path: String,
+        /// Config file to use instead of <PATH>/Nocti.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+    /// Scaffold a Nocti.toml for an existing project
+    Init {
+        #[arg(default_value = ".")]
+        path: String,
+        /// Overwrite an existing Nocti.toml (a Nocti.yaml or Nocti.yml is never replaced)
+        #[arg(long)]
+        force: bool,
     },
-    Push {
+    /// List the registered name to digest mappings
+    List {
+        /// Control plane URL (defaults to $NOCTI_CONTROL_PLANE_URL or http://localhost:50002)
+        #[arg(long)]
+        control_plane_url: Option<String>,
+        /// Print the mappings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the digest a name currently resolves to
+    Inspect {
+        key: String,
+        /// Control plane URL (defaults to $NOCTI_CONTROL_PLANE_URL or http://localhost:50002)
+        #[arg(long)]
+        control_plane_url: Option<String>,
+        /// Print the mapping as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Stream execution logs from the worker
+    Logs(logs::LogsArgs),
+    /// Print a shell completion script, e.g. `completions zsh > _noctiForge`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Check that the worker is reachable and healthy
+    Status {
+        /// Worker service URL (defaults to $NOCTI_WORKER_URL or http://[::1]:50003)
+        #[arg(long)]
+        worker_url: Option<String>,
+        /// Service name to check, the whole server when empty
+        #[arg(long, default_value = "")]
+        service: String,
+    },
+    /// Check the registry, control plane and worker are reachable
+    Doctor {
+        #[arg(default_value = ".")]
         path: String,
+        /// Config file to use instead of <PATH>/Nocti.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// Worker service URL (defaults to $NOCTI_WORKER_URL or http://[::1]:50003)
+        #[arg(long)]
+        worker_url: Option<String>,
+    },
+    /// Remove a name to digest mapping
+    Delete {
+        key: String,
+        /// Control plane URL (defaults to $NOCTI_CONTROL_PLANE_URL or http://localhost:50002)
+        #[arg(long)]
+        control_plane_url: Option<String>,
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
 }
 
@@ -37,36 +149,130 @@ struct Commands {
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
 
-    setup_tracing(cli.verbose)?;
+    let log_format = match cli.log_format {
+        Some(format) => format,
+        None => log_format_from_env()?,
+    };
+    setup_tracing(log_level(cli.verbose, cli.quiet), log_format)?;
 
     match cli.command {
-        Command::Trigger {
-            action,
-            payload,
-            metadata,
-        } => trigger::run(action, payload, metadata).await?,
-        Command::Push { path } => {
-            push::run(&path).await?;
+        Command::Completions { shell } => print_completions(shell),
+        Command::Trigger(args) => trigger::run(args, cli.quiet).await?,
+        Command::Push(args) => push::run(args, cli.quiet, cli.verbose > 0).await?,
+        Command::Pull(args) => pull::run(args).await?,
+        Command::Logs(args) => logs::run(args).await?,
+        Command::Build {
+            path,
+            config,
+            output,
+        } => {
+            build::run(&path, config.as_deref(), &output).await?;
+        }
+        Command::Validate { path, config } => {
+            validate::run(&path, config.as_deref(), cli.quiet).await?
         }
+        Command::Init { path, force } => init::run(&path, force)?,
+        Command::List {
+            control_plane_url,
+            json,
+        } => list::run(control_plane_url, json).await?,
+        Command::Inspect {
+            key,
+            control_plane_url,
+            json,
+        } => inspect::run(key, control_plane_url, json).await?,
+        Command::Delete {
+            key,
+            control_plane_url,
+            yes,
+        } => delete::run(key, control_plane_url, yes).await?,
+        Command::Status {
+            worker_url,
+            service,
+        } => status::run(worker_url, service, cli.quiet).await?,
+        Command::Doctor {
+            path,
+            config,
+            worker_url,
+        } => doctor::run(&path, config.as_deref(), worker_url, cli.quiet).await?,
     }
 
     Ok(())
 }
 
-fn setup_tracing(verbosity: u8) -> Result<()> {
-    let level = match verbosity {
+/// Write the completion script for `shell` to stdout
+fn print_completions(shell: Shell) {
+    clap_complete::generate(
+        shell,
+        &mut Cli::command(),
+        env!("CARGO_BIN_NAME"),
+        &mut std::io::stdout(),
+    );
+}
+
+fn log_format_from_env() -> Result<LogFormat> {
+    match std::env::var("NOCTI_LOG_FORMAT") {
+        Ok(value) => LogFormat::from_str(&value, true)
+            .map_err(|e| anyhow::anyhow!("Invalid NOCTI_LOG_FORMAT '{}': {}", value, e)),
+        Err(_) => Ok(LogFormat::Text),
+    }
+}
+
+/// Map the -v/-q flags to a tracing level, used unless RUST_LOG is set
+fn log_level(verbosity: u8, quiet: bool) -> &'static str {
+    if quiet {
+        return "error";
+    }
+
+    match verbosity {
         0 => "info",
         1 => "debug",
         _ => "trace",
-    };
+    }
+}
 
+fn setup_tracing(level: &str, log_format: LogFormat) -> Result<()> {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .or_else(|_| tracing_subscriber::EnvFilter::try_new(level))?;
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
-        .with_target(false)
-        .init();
+        .with_target(false);
+
+    match log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(log_level(0, false), "info");
+        assert_eq!(log_level(1, false), "debug");
+        assert_eq!(log_level(3, false), "trace");
+        assert_eq!(log_level(0, true), "error");
+    }
+
+    #[test]
+    fn test_completions_include_subcommands() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "noctiForge", &mut script);
+        let script = String::from_utf8(script).unwrap();
+
+        for subcommand in ["push", "trigger", "build", "pull", "completions"] {
+            assert!(script.contains(subcommand), "missing {}", subcommand);
+        }
+    }
+
+    #[test]
+    fn test_quiet_conflicts_with_verbose() {
+        assert!(Cli::try_parse_from(["noctiForge", "-q", "-v", "init"]).is_err());
+        assert!(Cli::try_parse_from(["noctiForge", "init", "-vv"]).is_ok());
+    }
+}
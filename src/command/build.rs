@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::info;
+
+use crate::command::push;
+
+/// Build the project and write the resulting (possibly compressed) tar archive to `output`
+pub async fn run(path: &str, config_path: Option<&Path>, output: &str) -> Result<()> {
+    let project_path = Path::new(path);
+    info!("Running build command on path: {:?}", project_path);
+
+    let config = push::load_config(project_path, config_path)?;
+    let artifact = push::build_artifact(project_path, config.build, false).await?;
+
+    let output_path = Path::new(output);
+    info!("Writing tar archive to: {:?}", output_path);
+    let file = tokio::fs::File::create(output_path)
+        .await
+        .with_context(|| format!("Failed to create output file: {:?}", output_path))?;
+
+    push::write_archive(file, project_path, &artifact.output, &config.archive)
+        .await
+        .with_context(|| format!("Failed to write output file: {:?}", output_path))?;
+
+    info!("Build archive written to {:?}", output_path);
+    Ok(())
+}
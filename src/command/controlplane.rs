@@ -0,0 +1,149 @@
+use anyhow::Context;
+use serde::Serialize;
+use tonic::Status;
+use tracing::info;
+
+use crate::command::transport::{self, AuthChannel, ConnectOptions, RawClient};
+
+/// Control plane calls that are not part of the generated `ControlPlaneServiceClient`
+pub struct ControlPlaneClient {
+    inner: RawClient,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListDigestsRequest {}
+
+#[derive(Clone, PartialEq, Serialize, prost::Message)]
+pub struct DigestMapping {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub digest: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListDigestsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub mappings: Vec<DigestMapping>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteNameRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteNameResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetDigestRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+}
+
+/// `GetDigestResponse` extended with the mapping's tag, which older control planes leave empty
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetDigestResponse {
+    #[prost(string, tag = "1")]
+    pub digest: String,
+    #[prost(string, tag = "2")]
+    pub tag: String,
+}
+
+/// `SetDigestToNameRequest` extended with a tag, which older control planes ignore
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SetDigestToNameRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub digest: String,
+    #[prost(string, tag = "3")]
+    pub tag: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SetDigestToNameResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// Why the mapping was rejected, empty when not given
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+/// Connect to the control plane at `url`
+pub async fn connect(url: &str, options: &ConnectOptions) -> anyhow::Result<ControlPlaneClient> {
+    info!("Connecting to ControlPlaneService at {}...", url);
+    let channel = transport::connect(url, options)
+        .await
+        .with_context(|| format!("Failed to connect to ControlPlaneService at {}", url))?;
+
+    Ok(ControlPlaneClient::new(channel, options))
+}
+
+impl ControlPlaneClient {
+    pub fn new(channel: AuthChannel, options: &ConnectOptions) -> Self {
+        Self {
+            inner: RawClient::new(channel, options),
+        }
+    }
+
+    /// List all name to digest mappings
+    pub async fn list_digests(&mut self) -> Result<Vec<DigestMapping>, Status> {
+        let response: ListDigestsResponse = self
+            .inner
+            .unary(
+                "/noctiforge.controlplane.ControlPlaneService/ListDigests",
+                ListDigestsRequest {},
+            )
+            .await?;
+
+        Ok(response.mappings)
+    }
+
+    /// Map a key to a digest, recording `tag` in the key's history when not empty
+    pub async fn set_digest_to_name(
+        &mut self,
+        key: String,
+        digest: String,
+        tag: String,
+    ) -> Result<SetDigestToNameResponse, Status> {
+        self.inner
+            .unary(
+                "/noctiforge.controlplane.ControlPlaneService/SetDigestToName",
+                SetDigestToNameRequest { key, digest, tag },
+            )
+            .await
+    }
+
+    /// Remove the digest mapping for a key, returning whether it was removed
+    pub async fn delete_name(&mut self, key: String) -> Result<bool, Status> {
+        let response: DeleteNameResponse = self
+            .inner
+            .unary(
+                "/noctiforge.controlplane.ControlPlaneService/DeleteName",
+                DeleteNameRequest { key },
+            )
+            .await?;
+
+        Ok(response.success)
+    }
+
+    /// Look up the digest currently mapped to a key
+    pub async fn get_digest(&mut self, key: String) -> Result<String, Status> {
+        Ok(self.get_mapping(key).await?.digest)
+    }
+
+    /// Look up the digest and tag currently mapped to a key
+    pub async fn get_mapping(&mut self, key: String) -> Result<GetDigestResponse, Status> {
+        self.inner
+            .unary(
+                "/noctiforge.controlplane.ControlPlaneService/GetDigest",
+                GetDigestRequest { key },
+            )
+            .await
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::{Context, Result, bail};
+use tonic::Code;
+use tracing::{error, info};
+
+use crate::command::controlplane;
+use crate::command::prompt;
+use crate::command::push::default_control_plane_url;
+use crate::command::transport::ConnectOptions;
+
+/// Remove the digest mapping for `key` from the control plane
+pub async fn run(key: String, control_plane_url: Option<String>, yes: bool) -> Result<()> {
+    if !yes && !prompt::confirm(&format!("Delete the mapping for '{}'?", key))? {
+        info!("Aborted, nothing was deleted");
+        return Ok(());
+    }
+
+    let control_plane_url = control_plane_url.unwrap_or_else(default_control_plane_url);
+
+    let connect_options = ConnectOptions::from_env();
+    let mut client = controlplane::connect(&control_plane_url, &connect_options).await?;
+
+    let deleted = match client.delete_name(key.clone()).await {
+        Ok(deleted) => deleted,
+        Err(status) if status.code() == Code::NotFound => false,
+        Err(status) => return Err(status).context("Failed to delete digest mapping"),
+    };
+
+    if deleted {
+        info!("Successfully deleted mapping for key '{}'", key);
+        Ok(())
+    } else {
+        error!("No mapping found for key '{}'", key);
+        bail!("Key '{}' does not exist", key)
+    }
+}
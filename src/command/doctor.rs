@@ -0,0 +1,205 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use tonic::Code;
+use tracing::{debug, info};
+
+use crate::command::health::{HealthClient, ServingStatus};
+use crate::command::push;
+use crate::command::status::{describe, format_latency};
+use crate::command::transport::{self, ConnectOptions};
+use crate::command::trigger::resolve_worker_url;
+
+/// A service reached by `doctor`
+#[derive(Debug, PartialEq)]
+struct Reachable {
+    connect: Duration,
+    /// Health status and check latency, `None` when the server has no health service
+    health: Option<(ServingStatus, Duration)>,
+}
+
+/// Outcome of checking one service
+struct Check {
+    service: &'static str,
+    url: String,
+    result: Result<Reachable>,
+}
+
+impl Check {
+    /// Whether the service is reachable and not reporting itself unhealthy
+    fn passed(&self) -> bool {
+        match self.result {
+            Ok(ref reachable) => reachable
+                .health
+                .is_none_or(|(status, _)| status == ServingStatus::Serving),
+            Err(_) => false,
+        }
+    }
+
+    /// One checklist line with the resolved URL and latencies or the failure
+    fn line(&self) -> String {
+        let mark = if self.passed() { "[ok]  " } else { "[FAIL]" };
+        let detail = match self.result {
+            Ok(ref reachable) => {
+                let health = match reachable.health {
+                    Some((status, latency)) => {
+                        format!("health {} {}", describe(status), format_latency(latency))
+                    }
+                    None => "no health service".to_string(),
+                };
+                format!("connect {}, {}", format_latency(reachable.connect), health)
+            }
+            Err(ref e) => format!("{:#}", e),
+        };
+
+        format!("{} {:<13} {} ({})", mark, self.service, self.url, detail)
+    }
+}
+
+/// Check the registry, control plane and worker are reachable, failing if any is not.
+/// When quiet the checks are only printed if one fails.
+pub async fn run(
+    path: &str,
+    config_path: Option<&Path>,
+    worker_url: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let project_path = Path::new(path);
+
+    // Outside a project the URLs and connection settings come from the environment
+    let (registry_url, control_plane_url, options) =
+        if config_path.is_some() || push::has_config_file(project_path) {
+            let config = push::load_config(project_path, config_path)?;
+            let options = config.connect_options();
+            (config.registry_url, config.control_plane_url, options)
+        } else {
+            info!(
+                "No config file in {:?}, using the environment",
+                project_path
+            );
+            (
+                push::default_registry_url(),
+                push::default_control_plane_url(),
+                ConnectOptions::from_env(),
+            )
+        };
+    let worker_url = resolve_worker_url(worker_url);
+
+    let (registry, control_plane, worker) = tokio::join!(
+        check("registry", registry_url, &options),
+        check("control plane", control_plane_url, &options),
+        check("worker", worker_url, &options),
+    );
+
+    let checks = [registry, control_plane, worker];
+    let failed = checks.iter().filter(|c| !c.passed()).count();
+    if !quiet || failed > 0 {
+        for check in &checks {
+            println!("{}", check.line());
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "{} of {} services are unreachable or unhealthy",
+            failed,
+            checks.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Connect to `url` and ask its health service whether it is serving
+async fn check(service: &'static str, url: String, options: &ConnectOptions) -> Check {
+    let result = reach(service, &url, options).await;
+    debug!(service, url = %url, ok = result.is_ok(), "Checked service");
+
+    Check {
+        service,
+        url,
+        result,
+    }
+}
+
+async fn reach(service: &str, url: &str, options: &ConnectOptions) -> Result<Reachable> {
+    transport::validate_url(url, service)?;
+
+    let started = Instant::now();
+    let channel = transport::connect(url, options).await?;
+    let connect = started.elapsed();
+
+    let started = Instant::now();
+    let health = match HealthClient::new(channel, options)
+        .check(String::new())
+        .await
+    {
+        Ok(status) => Some((status, started.elapsed())),
+        Err(status) if status.code() == Code::Unimplemented => None,
+        Err(status) => {
+            let request_timeout = options.timeouts.request_timeout();
+            return Err(transport::call_error(
+                status,
+                "check health",
+                request_timeout,
+            ))
+            .context("Connected, but the health check failed");
+        }
+    };
+
+    Ok(Reachable { connect, health })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reachable(health: Option<ServingStatus>) -> Check {
+        Check {
+            service: "registry",
+            url: "http://localhost:50001".to_string(),
+            result: Ok(Reachable {
+                connect: Duration::from_micros(1_500),
+                health: health.map(|status| (status, Duration::from_micros(500))),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_check_line() {
+        assert_eq!(
+            reachable(Some(ServingStatus::Serving)).line(),
+            "[ok]   registry      http://localhost:50001 (connect 1.5ms, health serving 0.5ms)"
+        );
+        assert_eq!(
+            reachable(None).line(),
+            "[ok]   registry      http://localhost:50001 (connect 1.5ms, no health service)"
+        );
+    }
+
+    #[test]
+    fn test_check_passed() {
+        assert!(reachable(Some(ServingStatus::Serving)).passed());
+        assert!(reachable(None).passed());
+        assert!(!reachable(Some(ServingStatus::NotServing)).passed());
+
+        let unreachable = Check {
+            result: Err(anyhow::anyhow!("Failed to connect")),
+            ..reachable(None)
+        };
+        assert!(!unreachable.passed());
+        assert!(unreachable.line().starts_with("[FAIL] registry"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url_fails_check() {
+        let check = check(
+            "worker",
+            "ftp://worker".to_string(),
+            &ConnectOptions::from_env(),
+        )
+        .await;
+        assert!(!check.passed());
+    }
+}
@@ -0,0 +1,50 @@
+use tonic::Status;
+
+use crate::command::transport::{AuthChannel, ConnectOptions, RawClient};
+
+/// Client for the standard gRPC health checking protocol (`grpc.health.v1`)
+pub struct HealthClient {
+    inner: RawClient,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    pub service: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HealthCheckResponse {
+    #[prost(enumeration = "ServingStatus", tag = "1")]
+    pub status: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum ServingStatus {
+    Unknown = 0,
+    Serving = 1,
+    NotServing = 2,
+    ServiceUnknown = 3,
+}
+
+impl HealthClient {
+    pub fn new(channel: AuthChannel, options: &ConnectOptions) -> Self {
+        Self {
+            inner: RawClient::new(channel, options),
+        }
+    }
+
+    /// Ask the server for the serving status of `service`, or of the whole server when empty
+    pub async fn check(&mut self, service: String) -> Result<ServingStatus, Status> {
+        let response: HealthCheckResponse = self
+            .inner
+            .unary(
+                "/grpc.health.v1.Health/Check",
+                HealthCheckRequest { service },
+            )
+            .await?;
+
+        Ok(ServingStatus::try_from(response.status).unwrap_or(ServingStatus::Unknown))
+    }
+}
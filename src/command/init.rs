@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, info};
+
+use crate::command::push::{CONFIG_FILE, existing_config_files};
+
+/// Scaffold a `Nocti.toml` in the given directory
+pub fn run(path: &str, force: bool) -> Result<()> {
+    let project_path = Path::new(path);
+    info!("Initializing project at: {:?}", project_path);
+
+    if !project_path.is_dir() {
+        bail!("path does not exist or is not a directory");
+    }
+
+    let config_file_path = project_path.join(CONFIG_FILE);
+    let existing = existing_config_files(project_path);
+
+    // A YAML config would make the project's config ambiguous, and is the user's to remove
+    if let Some(path) = existing.iter().find(|path| **path != config_file_path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        bail!(
+            "'{}' already exists, remove it or rename it to '{}.bak' before running init",
+            name,
+            name
+        );
+    }
+
+    if existing.contains(&config_file_path) && !force {
+        bail!(
+            "'{}' already exists, pass --force to overwrite it",
+            CONFIG_FILE
+        );
+    }
+
+    let content = generate_config(project_path)?;
+    debug!("Generated config:\n{}", content);
+
+    std::fs::write(&config_file_path, content)
+        .with_context(|| format!("Failed to write config file: {:?}", config_file_path))?;
+
+    info!("Created {:?}", config_file_path);
+    Ok(())
+}
+
+/// Generate the config file contents for a project
+fn generate_config(project_path: &Path) -> Result<String> {
+    let cargo_toml = project_path.join("Cargo.toml");
+    let is_rust = cargo_toml.is_file();
+
+    let package_name = if is_rust {
+        crate_name(&cargo_toml)?
+    } else {
+        None
+    };
+    let name = package_name
+        .or_else(|| directory_name(project_path))
+        .unwrap_or_else(|| "my-project".to_string());
+
+    let build = if is_rust {
+        debug!("Found Cargo.toml, using rust build");
+        "[build]\n\
+         type = \"rust\"\n\
+         profile = \"release\"\n\
+         target = \"x86_64-unknown-linux-musl\"\n"
+    } else {
+        debug!("No Cargo.toml found, using custom build");
+        "[build]\n\
+         type = \"custom\"\n\
+         # The script must write the artifact to $OUTPUT/$ENTRYPOINT\n\
+         script = \"cp ./bootstrap $OUTPUT/$ENTRYPOINT\"\n"
+    };
+
+    Ok(format!(
+        "# registry_url = \"http://localhost:50001\"\n\
+         # control_plane_url = \"http://localhost:50002\"\n\
+         \n\
+         [project]\n\
+         name = {}\n\
+         \n\
+         {}",
+        toml::Value::String(name),
+        build
+    ))
+}
+
+/// Read the package name from a Cargo.toml, if it has one
+fn crate_name(cargo_toml: &Path) -> Result<Option<String>> {
+    let content = std::fs::read_to_string(cargo_toml)
+        .with_context(|| format!("Failed to read {:?}", cargo_toml))?;
+    let manifest: toml::Table =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {:?}", cargo_toml))?;
+
+    Ok(manifest
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string))
+}
+
+fn directory_name(project_path: &Path) -> Option<String> {
+    project_path
+        .canonicalize()
+        .ok()?
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::push::{Build, Config};
+
+    fn read_config(dir: &Path) -> Config {
+        let content = std::fs::read_to_string(dir.join(CONFIG_FILE)).unwrap();
+        toml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn test_init_rust_project() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"my-handler\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+
+        run(dir.path().to_str().unwrap(), false).unwrap();
+
+        let config = read_config(dir.path());
+        assert_eq!(config.project.name.as_deref(), Some("my-handler"));
+        assert!(matches!(config.build, Build::Rust(_)));
+    }
+
+    #[test]
+    fn test_init_custom_project() {
+        let dir = tempfile::tempdir().unwrap();
+
+        run(dir.path().to_str().unwrap(), false).unwrap();
+
+        let config = read_config(dir.path());
+        let dir_name = directory_name(dir.path()).unwrap();
+        assert_eq!(config.project.name, Some(dir_name));
+        assert!(matches!(config.build, Build::Custom(_)));
+    }
+
+    #[test]
+    fn test_init_refuses_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE), "existing").unwrap();
+
+        assert!(run(dir.path().to_str().unwrap(), false).is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(CONFIG_FILE)).unwrap(),
+            "existing"
+        );
+
+        run(dir.path().to_str().unwrap(), true).unwrap();
+        read_config(dir.path());
+    }
+
+    #[test]
+    fn test_init_refuses_yaml_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Nocti.yaml"), "existing").unwrap();
+
+        let err = run(dir.path().to_str().unwrap(), false).unwrap_err();
+        assert!(err.to_string().contains("Nocti.yaml"));
+        assert!(!dir.path().join(CONFIG_FILE).exists());
+
+        let err = run(dir.path().to_str().unwrap(), true).unwrap_err();
+        assert!(err.to_string().contains("Nocti.yaml.bak"));
+        assert!(!dir.path().join(CONFIG_FILE).exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("Nocti.yaml")).unwrap(),
+            "existing"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::{Context, Result, bail};
+use tonic::Code;
+use tracing::{debug, error, info};
+
+use crate::command::controlplane::{self, DigestMapping};
+use crate::command::push::default_control_plane_url;
+use crate::command::transport::ConnectOptions;
+
+/// Print the digest currently mapped to `key`
+pub async fn run(key: String, control_plane_url: Option<String>, json: bool) -> Result<()> {
+    let control_plane_url = control_plane_url.unwrap_or_else(default_control_plane_url);
+
+    let connect_options = ConnectOptions::from_env();
+    let mut client = controlplane::connect(&control_plane_url, &connect_options).await?;
+
+    let digest = match client.get_digest(key.clone()).await {
+        Ok(digest) if !digest.is_empty() => digest,
+        Ok(_) => {
+            error!("No mapping found for key '{}'", key);
+            bail!("Key '{}' not found", key)
+        }
+        Err(status) if status.code() == Code::NotFound => {
+            error!("No mapping found for key '{}'", key);
+            bail!("Key '{}' not found", key)
+        }
+        Err(status) => return Err(status).context("Failed to look up digest"),
+    };
+    debug!(key = %key, digest = %digest, "Resolved key");
+
+    if json {
+        let mapping = DigestMapping { key, digest };
+        println!("{}", serde_json::to_string_pretty(&mapping)?);
+        return Ok(());
+    }
+
+    println!("{}", digest);
+
+    Ok(())
+}
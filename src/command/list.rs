@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use tracing::{debug, info};
+
+use crate::command::controlplane;
+use crate::command::push::default_control_plane_url;
+use crate::command::transport::ConnectOptions;
+
+/// Print every name to digest mapping registered in the control plane
+pub async fn run(control_plane_url: Option<String>, json: bool) -> Result<()> {
+    let control_plane_url = control_plane_url.unwrap_or_else(default_control_plane_url);
+
+    let connect_options = ConnectOptions::from_env();
+    let mut client = controlplane::connect(&control_plane_url, &connect_options).await?;
+
+    let mut mappings = client
+        .list_digests()
+        .await
+        .context("Failed to list digest mappings")?;
+    mappings.sort_by(|a, b| a.key.cmp(&b.key));
+    debug!("Control plane returned {} mappings", mappings.len());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&mappings)?);
+        return Ok(());
+    }
+
+    let width = mappings
+        .iter()
+        .map(|m| m.key.len())
+        .max()
+        .unwrap_or(0)
+        .max("KEY".len());
+
+    println!("{:<width$}  DIGEST", "KEY");
+    for mapping in &mappings {
+        println!("{:<width$}  {}", mapping.key, mapping.digest);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use tonic::Status;
+use tracing::{debug, info, warn};
+
+use crate::command::transport::{self, ConnectOptions, RetryPolicy};
+use crate::command::trigger::{resolve_worker_url, validate_worker_url};
+use crate::command::worker::{LogEntry, StreamLogsRequest, WorkerClient};
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Only show lines of this action
+    action: Option<String>,
+    /// Worker service URL (defaults to $NOCTI_WORKER_URL or http://[::1]:50003)
+    #[arg(long)]
+    worker_url: Option<String>,
+    /// Keep streaming new lines, reconnecting when the stream drops
+    #[arg(short, long)]
+    follow: bool,
+    /// Start with lines from this long ago, e.g. 90, 30s, 10m or 2h
+    #[arg(long, value_name = "DURATION", value_parser = parse_since)]
+    since: Option<Duration>,
+}
+
+/// Parse a duration of whole seconds, minutes, hours or days, seconds when unsuffixed
+fn parse_since(value: &str) -> Result<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration '{}'", value))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => bail!("Invalid duration unit in '{}', use s, m, h or d", value),
+    };
+    let seconds = number
+        .checked_mul(unit_seconds)
+        .with_context(|| format!("Duration '{}' is too long", value))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// The `since_seconds` to reconnect with: from just before the last entry printed, or
+/// the previous window stretched over the failed attempt when nothing arrived
+fn resume_since(since_seconds: u64, started: Instant, last_seen: Option<Instant>) -> u64 {
+    match last_seen {
+        Some(seen) => seen.elapsed().as_secs() + 1,
+        None => since_seconds + started.elapsed().as_secs() + 1,
+    }
+}
+
+fn print_entry(entry: &LogEntry) {
+    println!("{} [{}] {}", entry.timestamp, entry.action, entry.line);
+}
+
+/// Stream worker logs until the stream ends, or with `--follow` until interrupted
+pub async fn run(args: LogsArgs) -> Result<()> {
+    let LogsArgs {
+        action,
+        worker_url,
+        follow,
+        since,
+    } = args;
+
+    let worker_url = resolve_worker_url(worker_url);
+    validate_worker_url(&worker_url)?;
+
+    // Log streams stay open for as long as the user wants them
+    let mut connect_options = ConnectOptions::from_env();
+    connect_options.timeouts.request_seconds = Some(0);
+
+    info!("Connecting to WorkerService at {}...", worker_url);
+    let channel = transport::connect(&worker_url, &connect_options).await?;
+    let mut client = WorkerClient::new(channel, &connect_options);
+
+    let retry_policy = RetryPolicy::from_env();
+    let mut since_seconds = since.map_or(0, |since| since.as_secs());
+    let mut attempt = 0;
+    loop {
+        let request = StreamLogsRequest {
+            action: action.clone().unwrap_or_default(),
+            since_seconds,
+            follow,
+        };
+        let started = Instant::now();
+        let mut last_seen = None;
+        let ended = match client.stream_logs(request).await {
+            Ok(mut stream) => loop {
+                match stream.message().await {
+                    Ok(Some(entry)) => {
+                        print_entry(&entry);
+                        last_seen = Some(Instant::now());
+                        attempt = 0;
+                    }
+                    Ok(None) => break Ok(()),
+                    Err(status) => break Err(status),
+                }
+            },
+            Err(status) => Err(status),
+        };
+
+        if !follow {
+            debug!("Log stream ended");
+            return ended.map_err(|e| transport::call_error(e, "stream worker logs", None));
+        }
+
+        let reason = match ended {
+            Ok(()) => "the worker closed the stream".to_string(),
+            Err(status) if transport::is_retryable_status(&status) => status.message().to_string(),
+            Err(status) => return Err(transport::call_error(status, "stream worker logs", None)),
+        };
+        if attempt >= retry_policy.retries {
+            return Err(Status::unavailable(reason))
+                .with_context(|| format!("Lost the log stream after {} reconnects", attempt));
+        }
+
+        let delay = retry_policy.delay(attempt);
+        attempt += 1;
+        warn!(
+            "Log stream interrupted ({}), reconnecting in {:?} ({}/{})",
+            reason, delay, attempt, retry_policy.retries
+        );
+        tokio::time::sleep(delay).await;
+
+        // Resume from the last line seen, overlapping by a second rather than missing lines
+        since_seconds = resume_since(since_seconds, started, last_seen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_since("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_since("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_since("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_since("1d").unwrap(), Duration::from_secs(86400));
+    }
+
+    #[test]
+    fn test_resume_since() {
+        let started = Instant::now() - Duration::from_secs(10);
+        assert_eq!(resume_since(3600, started, None), 3611);
+        assert_eq!(resume_since(0, started, None), 11);
+
+        let seen = Instant::now() - Duration::from_secs(5);
+        assert_eq!(resume_since(3600, started, Some(seen)), 6);
+    }
+
+    #[test]
+    fn test_parse_since_invalid() {
+        assert!(parse_since("").is_err());
+        assert!(parse_since("m").is_err());
+        assert!(parse_since("5w").is_err());
+        assert!(parse_since("-5s").is_err());
+    }
+}
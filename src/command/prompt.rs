@@ -0,0 +1,54 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result, bail};
+
+/// Ask the user a yes/no question on the terminal, defaulting to no.
+/// Fails when stdin is not interactive, since nobody can answer.
+pub fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Cannot ask for confirmation in a non-interactive session, pass --yes instead");
+    }
+
+    Ok(is_yes(&read_answer(&format!("{} [y/N] ", question))?))
+}
+
+/// Ask the user to type `expected` to confirm, failing when stdin is not interactive
+pub fn confirm_typed(question: &str, expected: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Cannot ask for confirmation in a non-interactive session, pass --yes instead");
+    }
+
+    let answer = read_answer(&format!("{} Type '{}' to continue: ", question, expected))?;
+    Ok(answer.trim() == expected)
+}
+
+/// Print `prompt` and read one line from stdin
+fn read_answer(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+
+    Ok(answer)
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_yes() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("no"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use clap::Args;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tonic::Code;
+use tracing::{debug, error, info};
+
+use crate::command::controlplane;
+use crate::command::push::{DIGEST_PREFIX, default_control_plane_url, default_registry_url};
+use crate::command::registry::RegistryClient;
+use crate::command::transport::{self, ConnectOptions};
+
+#[derive(Args, Debug)]
+pub struct PullArgs {
+    /// Project name, or a digest of the form sha256:<hex>
+    reference: String,
+    /// Path of the tar archive to write
+    #[arg(short, long, value_name = "FILE")]
+    output: PathBuf,
+    /// Also unpack the archive into this directory
+    #[arg(long, value_name = "DIR")]
+    extract: Option<PathBuf>,
+    /// Registry URL (defaults to $NOCTI_REGISTRY_URL or http://localhost:50001)
+    #[arg(long)]
+    registry_url: Option<String>,
+    /// Control plane URL (defaults to $NOCTI_CONTROL_PLANE_URL or http://localhost:50002)
+    #[arg(long)]
+    control_plane_url: Option<String>,
+}
+
+pub async fn run(args: PullArgs) -> Result<()> {
+    let PullArgs {
+        reference,
+        output,
+        extract,
+        registry_url,
+        control_plane_url,
+    } = args;
+
+    let connect_options = ConnectOptions::from_env();
+
+    let digest = if reference.starts_with(DIGEST_PREFIX) {
+        reference
+    } else {
+        let control_plane_url = control_plane_url.unwrap_or_else(default_control_plane_url);
+        resolve_digest(&control_plane_url, &connect_options, &reference).await?
+    };
+    info!(digest = %digest, "Pulling artifact");
+
+    let registry_url = registry_url.unwrap_or_else(default_registry_url);
+    let channel = transport::connect(&registry_url, &connect_options)
+        .await
+        .with_context(|| format!("Failed to connect to RegistryService at {}", registry_url))?;
+    let mut stream = RegistryClient::new(channel, &connect_options)
+        .pull(digest.clone())
+        .await
+        .context("Failed to pull from registry")?;
+
+    // Download next to the output and only move it into place once verified, so a failed
+    // pull never leaves a truncated archive behind
+    let (file, temp_path) = partial_file(&output)?.into_parts();
+    let mut file = tokio::fs::File::from_std(file);
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+
+    while let Some(chunk) = stream
+        .message()
+        .await
+        .context("Failed to read from registry stream")?
+    {
+        debug!("Received {} bytes from registry", chunk.data.len());
+        hasher.update(&chunk.data);
+        size += chunk.data.len();
+        file.write_all(&chunk.data)
+            .await
+            .with_context(|| format!("Failed to write output file: {:?}", output))?;
+    }
+    file.flush()
+        .await
+        .with_context(|| format!("Failed to write output file: {:?}", output))?;
+
+    let actual = format!("{}{:x}", DIGEST_PREFIX, hasher.finalize());
+    if let Err(e) = verify_digest(&digest, &actual) {
+        error!("Downloaded artifact does not match digest {}", digest);
+        return Err(e);
+    }
+    temp_path
+        .persist(&output)
+        .with_context(|| format!("Failed to write output file: {:?}", output))?;
+    info!(digest = %digest, size, "Artifact written to {:?}", output);
+
+    if let Some(dir) = extract {
+        extract_archive(&output, &dir).await?;
+        info!("Artifact extracted to {:?}", dir);
+    }
+
+    Ok(())
+}
+
+/// Create a temporary file beside `output`, removed unless it is persisted
+fn partial_file(output: &Path) -> Result<tempfile::NamedTempFile> {
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".nocti-pull");
+    // Temporary files are private by default, the archive should not end up that way
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        builder.permissions(std::fs::Permissions::from_mode(0o644));
+    }
+
+    builder
+        .tempfile_in(dir)
+        .with_context(|| format!("Failed to create output file: {:?}", output))
+}
+
+/// Look up the digest currently mapped to the project name
+async fn resolve_digest(
+    control_plane_url: &str,
+    connect_options: &ConnectOptions,
+    name: &str,
+) -> Result<String> {
+    let mut client = controlplane::connect(control_plane_url, connect_options).await?;
+    match client.get_digest(name.to_string()).await {
+        Ok(digest) if !digest.is_empty() => Ok(digest),
+        Ok(_) => bail!("Key '{}' does not exist", name),
+        Err(status) if status.code() == Code::NotFound => bail!("Key '{}' does not exist", name),
+        Err(status) => Err(status).context("Failed to resolve digest"),
+    }
+}
+
+/// Check the computed digest matches the requested one
+fn verify_digest(expected: &str, actual: &str) -> Result<()> {
+    if !expected.eq_ignore_ascii_case(actual) {
+        bail!(
+            "Downloaded artifact digest does not match:\n  expected: {}\n  actual:   {}",
+            expected,
+            actual
+        );
+    }
+
+    Ok(())
+}
+
+/// Unpack the archive into `dir`, decompressing gzip or zstd archives first
+async fn extract_archive(archive: &Path, dir: &Path) -> Result<()> {
+    let file = tokio::fs::File::open(archive)
+        .await
+        .with_context(|| format!("Failed to open {:?}", archive))?;
+    let mut reader = BufReader::new(file);
+
+    let magic: Vec<u8> = reader
+        .fill_buf()
+        .await
+        .with_context(|| format!("Failed to read {:?}", archive))?
+        .iter()
+        .take(4)
+        .copied()
+        .collect();
+    let reader: Box<dyn AsyncRead + Unpin + Send> = match magic.as_slice() {
+        [0x1f, 0x8b, ..] => Box::new(GzipDecoder::new(reader)),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Box::new(ZstdDecoder::new(reader)),
+        _ => Box::new(reader),
+    };
+
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create {:?}", dir))?;
+    tokio_tar::Archive::new(reader)
+        .unpack(dir)
+        .await
+        .with_context(|| format!("Failed to extract {:?} to {:?}", archive, dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_file_is_removed_unless_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("artifact.tar");
+
+        let partial = partial_file(&output).unwrap();
+        assert_eq!(partial.path().parent(), Some(dir.path()));
+        drop(partial);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let (_, temp_path) = partial_file(&output).unwrap().into_parts();
+        temp_path.persist(&output).unwrap();
+        assert!(output.is_file());
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        let digest = format!("{}{:x}", DIGEST_PREFIX, Sha256::digest(b"artifact"));
+        assert!(verify_digest(&digest, &digest).is_ok());
+        assert!(verify_digest(&digest.to_uppercase(), &digest).is_ok());
+        assert!(verify_digest("sha256:00", &digest).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_extract_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("artifact.tar");
+
+        let file = tokio::fs::File::create(&archive_path).await.unwrap();
+        let mut builder = tokio_tar::Builder::new(file);
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "bootstrap", &b"bin"[..])
+            .await
+            .unwrap();
+        builder.into_inner().await.unwrap().flush().await.unwrap();
+
+        let target = dir.path().join("out");
+        extract_archive(&archive_path, &target).await.unwrap();
+        assert_eq!(std::fs::read(target.join("bootstrap")).unwrap(), b"bin");
+    }
+}
@@ -0,0 +1,712 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use async_compression::Level;
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Deserialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_tar::{Header, HeaderMode};
+use tracing::debug;
+
+const IGNORE_FILE: &str = ".noctiignore";
+
+/// Compression applied to the tar stream
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Settings controlling how the build output is archived
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ArchiveConfig {
+    /// Compression algorithm for the tar stream
+    #[serde(default)]
+    pub compression: Compression,
+
+    /// Algorithm specific compression level, the encoder default when unset
+    #[serde(default)]
+    pub compression_level: Option<i32>,
+
+    /// Normalize timestamps, ownership and permissions so identical builds
+    /// produce identical archives. Entry mtimes come from `SOURCE_DATE_EPOCH`
+    /// when set and are the fixed Unix epoch (0) otherwise
+    #[serde(default)]
+    pub reproducible: bool,
+
+    /// Permission bits cleared from every entry, e.g. `0o022`
+    #[serde(default)]
+    pub umask: Option<u32>,
+
+    /// Directory the build output is nested under, e.g. "app" for `app/bootstrap`
+    /// (default: ".", the archive root)
+    #[serde(default)]
+    pub package_root: Option<PathBuf>,
+}
+
+impl ArchiveConfig {
+    /// Normalized package root, `None` when entries are placed at the archive root
+    pub fn package_root(&self) -> Result<Option<PathBuf>> {
+        let Some(ref root) = self.package_root else {
+            return Ok(None);
+        };
+
+        let mut normalized = PathBuf::new();
+        for component in root.components() {
+            match component {
+                Component::Normal(name) => normalized.push(name),
+                Component::CurDir => {}
+                _ => bail!(
+                    "package_root must be a relative path without '..', got {:?}",
+                    root
+                ),
+            }
+        }
+
+        if normalized.as_os_str().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(normalized))
+    }
+}
+
+/// How entry headers are normalized when writing the archive
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HeaderOptions {
+    mode: HeaderMode,
+    mtime: Option<u64>,
+    umask: Option<u32>,
+}
+
+impl HeaderOptions {
+    /// Resolve the header options from the config and `SOURCE_DATE_EPOCH`, or
+    /// `None` when entries can be archived as they are on disk
+    fn new(config: &ArchiveConfig, source_date_epoch: Option<u64>) -> Option<Self> {
+        if config.reproducible {
+            return Some(Self {
+                mode: HeaderMode::Deterministic,
+                mtime: Some(source_date_epoch.unwrap_or(0)),
+                umask: config.umask,
+            });
+        }
+
+        if source_date_epoch.is_none() && config.umask.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            mode: HeaderMode::Complete,
+            mtime: source_date_epoch,
+            umask: config.umask,
+        })
+    }
+}
+
+/// Wrap the writer with the encoder for the configured compression
+fn compressed_writer<W>(writer: W, config: &ArchiveConfig) -> Box<dyn AsyncWrite + Unpin + Send>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let level = config
+        .compression_level
+        .map(Level::Precise)
+        .unwrap_or(Level::Default);
+
+    match config.compression {
+        Compression::None => Box::new(writer),
+        Compression::Gzip => Box::new(GzipEncoder::with_quality(writer, level)),
+        Compression::Zstd => Box::new(ZstdEncoder::with_quality(writer, level)),
+    }
+}
+
+/// Load the `.noctiignore` file from the project root, if present
+pub fn load_ignore(project_path: &Path) -> Result<Gitignore> {
+    load_ignore_with(project_path, &[])
+}
+
+/// Like [`load_ignore`], with the gitignore style `extra` lines added after the file's rules
+pub fn load_ignore_with(project_path: &Path, extra: &[&str]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(project_path);
+
+    let ignore_path = project_path.join(IGNORE_FILE);
+    if ignore_path.is_file() {
+        debug!("Loading ignore rules from: {:?}", ignore_path);
+        if let Some(e) = builder.add(&ignore_path) {
+            return Err(e).with_context(|| format!("Failed to parse {:?}", ignore_path));
+        }
+    }
+
+    for line in extra {
+        builder
+            .add_line(None, line)
+            .with_context(|| format!("Invalid ignore rule '{}'", line))?;
+    }
+
+    builder
+        .build()
+        .with_context(|| format!("Failed to build ignore rules from {:?}", ignore_path))
+}
+
+/// Collect all entries below `root` that are not ignored, as (absolute, relative) pairs
+pub fn collect_entries(root: &Path, ignore: &Gitignore) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let read_dir =
+            std::fs::read_dir(&dir).with_context(|| format!("Failed to read {:?}", dir))?;
+
+        for entry in read_dir {
+            let entry = entry.with_context(|| format!("Failed to read entry in {:?}", dir))?;
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(root)
+                .context("Entry is outside of the archive root")?
+                .to_path_buf();
+            let is_dir = entry
+                .file_type()
+                .with_context(|| format!("Failed to read file type of {:?}", path))?
+                .is_dir();
+
+            if ignore
+                .matched_path_or_any_parents(&relative, is_dir)
+                .is_ignore()
+            {
+                debug!("Ignoring {:?}", relative);
+                continue;
+            }
+
+            if is_dir {
+                pending.push(path.clone());
+            }
+            entries.push((path, relative));
+        }
+    }
+
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(entries)
+}
+
+/// A file that will be included in the archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// List the files below `root` that would be written to the archive
+pub fn manifest(root: &Path, ignore: &Gitignore) -> Result<Vec<ManifestEntry>> {
+    let mut files = Vec::new();
+
+    for (path, relative) in collect_entries(root, ignore)? {
+        let metadata = std::fs::metadata(&path)
+            .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+        if metadata.is_file() {
+            files.push(ManifestEntry {
+                path: relative,
+                size: metadata.len(),
+            });
+        }
+    }
+
+    Ok(files)
+}
+
+/// Debug log of the files appended to an archive
+#[derive(Debug, Default)]
+struct ManifestSummary {
+    files: usize,
+    bytes: u64,
+}
+
+impl ManifestSummary {
+    /// Log a file appended to the archive and add it to the totals
+    fn record(&mut self, relative: &Path, metadata: &std::fs::Metadata) {
+        if metadata.is_file() {
+            debug!(path = ?relative, size = metadata.len(), "Adding file to archive");
+            self.files += 1;
+            self.bytes += metadata.len();
+        }
+    }
+
+    fn log(&self) {
+        debug!(
+            files = self.files,
+            bytes = self.bytes,
+            "Archived files (before compression)"
+        );
+    }
+}
+
+/// Write every non-ignored entry below `root` as a (possibly compressed) tar
+/// archive into `writer`, shutting the writer down once it is complete
+pub async fn write_archive<W>(
+    writer: W,
+    root: &Path,
+    ignore: &Gitignore,
+    config: &ArchiveConfig,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let entries = nest_entries(collect_entries(root, ignore)?, root, config)?;
+    write_entries(writer, entries, config).await
+}
+
+/// Write `path` as the only entry, named `name`, of a (possibly compressed) tar
+/// archive into `writer`, shutting the writer down once it is complete
+pub async fn write_file_archive<W>(
+    writer: W,
+    path: &Path,
+    name: &str,
+    config: &ArchiveConfig,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let dir = path
+        .parent()
+        .with_context(|| format!("{:?} has no parent directory", path))?;
+    let entries = nest_entries(vec![(path.to_path_buf(), PathBuf::from(name))], dir, config)?;
+    write_entries(writer, entries, config).await
+}
+
+/// Move `entries` below the configured package root, preceded by an entry for the
+/// root itself taking its metadata from `dir`
+fn nest_entries(
+    entries: Vec<(PathBuf, PathBuf)>,
+    dir: &Path,
+    config: &ArchiveConfig,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let Some(package_root) = config.package_root()? else {
+        return Ok(entries);
+    };
+
+    debug!("Nesting archive entries under {:?}", package_root);
+    let nested = entries
+        .into_iter()
+        .map(|(path, relative)| (path, package_root.join(relative)));
+    Ok(std::iter::once((dir.to_path_buf(), package_root.clone()))
+        .chain(nested)
+        .collect())
+}
+
+/// Write `entries`, as (absolute, relative) pairs, into a (possibly compressed) tar archive
+async fn write_entries<W>(
+    writer: W,
+    entries: Vec<(PathBuf, PathBuf)>,
+    config: &ArchiveConfig,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    debug!("Writing archive with {:?} compression", config.compression);
+    let writer = compressed_writer(writer, config);
+
+    let mut writer = match HeaderOptions::new(config, source_date_epoch()) {
+        Some(options) => write_normalized_tar(writer, entries, options).await?,
+        None => write_tar(writer, entries).await?,
+    };
+    writer
+        .shutdown()
+        .await
+        .context("Failed to finish writing archive")?;
+
+    Ok(())
+}
+
+/// Write `entries` as a tar archive into `writer`, returning the writer once the
+/// archive is finalized
+async fn write_tar<W>(writer: W, entries: Vec<(PathBuf, PathBuf)>) -> Result<W>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let mut builder = tokio_tar::Builder::new(writer);
+    let mut summary = ManifestSummary::default();
+
+    for (path, relative) in entries {
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let metadata = tokio::fs::metadata(&path)
+                .await
+                .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+            summary.record(&relative, &metadata);
+        }
+
+        builder
+            .append_path_with_name(&path, &relative)
+            .await
+            .with_context(|| format!("Failed to add {:?} to tar", relative))?;
+    }
+
+    summary.log();
+    builder.into_inner().await.context("Failed to finalize tar")
+}
+
+/// Timestamp from `SOURCE_DATE_EPOCH`, if set
+fn source_date_epoch() -> Option<u64> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+}
+
+/// Like [`write_tar`], but with explicit headers normalized according to `options`
+async fn write_normalized_tar<W>(
+    writer: W,
+    entries: Vec<(PathBuf, PathBuf)>,
+    options: HeaderOptions,
+) -> Result<W>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    debug!("Writing tar with normalized headers: {:?}", options);
+    let mut builder = tokio_tar::Builder::new(writer);
+    let mut summary = ManifestSummary::default();
+
+    // Entries are already sorted by path, so only the headers need normalizing
+    for (path, relative) in entries {
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+        summary.record(&relative, &metadata);
+
+        let mut header = Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, options.mode);
+        if let Some(mtime) = options.mtime {
+            header.set_mtime(mtime);
+        }
+        if let Some(umask) = options.umask {
+            let mode = header
+                .mode()
+                .with_context(|| format!("Failed to read mode of {:?}", relative))?;
+            header.set_mode(mode & !umask);
+        }
+
+        let result = if metadata.is_dir() {
+            header.set_size(0);
+            builder
+                .append_data(&mut header, &relative, tokio::io::empty())
+                .await
+        } else {
+            let file = tokio::fs::File::open(&path)
+                .await
+                .with_context(|| format!("Failed to open {:?}", path))?;
+            builder.append_data(&mut header, &relative, file).await
+        };
+        result.with_context(|| format!("Failed to add {:?} to tar", relative))?;
+    }
+
+    summary.log();
+    builder.into_inner().await.context("Failed to finalize tar")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    fn relative_paths(root: &Path, ignore: &Gitignore) -> Vec<PathBuf> {
+        collect_entries(root, ignore)
+            .unwrap()
+            .into_iter()
+            .map(|(_, relative)| relative)
+            .collect()
+    }
+
+    #[test]
+    fn test_no_ignore_file() {
+        let project = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+        std::fs::write(output.path().join("build.log"), "log").unwrap();
+
+        let ignore = load_ignore(project.path()).unwrap();
+        assert_eq!(
+            relative_paths(output.path(), &ignore),
+            vec![PathBuf::from("bootstrap"), PathBuf::from("build.log")]
+        );
+    }
+
+    #[test]
+    fn test_ignore_glob() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join(IGNORE_FILE), "*.log\n").unwrap();
+
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+        std::fs::write(output.path().join("build.log"), "log").unwrap();
+
+        let ignore = load_ignore(project.path()).unwrap();
+        assert_eq!(
+            relative_paths(output.path(), &ignore),
+            vec![PathBuf::from("bootstrap")]
+        );
+    }
+
+    #[test]
+    fn test_manifest_lists_files() {
+        let project = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+        std::fs::create_dir(output.path().join("lib")).unwrap();
+        std::fs::write(output.path().join("lib").join("data"), "12345").unwrap();
+
+        let ignore = load_ignore(project.path()).unwrap();
+        assert_eq!(
+            manifest(output.path(), &ignore).unwrap(),
+            vec![
+                ManifestEntry {
+                    path: PathBuf::from("bootstrap"),
+                    size: 3
+                },
+                ManifestEntry {
+                    path: PathBuf::from("lib").join("data"),
+                    size: 5
+                },
+            ]
+        );
+    }
+
+    async fn archive_bytes(root: &Path, config: &ArchiveConfig) -> Vec<u8> {
+        let project = tempfile::tempdir().unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive_path = archive_dir.path().join("archive");
+        let file = tokio::fs::File::create(&archive_path).await.unwrap();
+
+        let ignore = load_ignore(project.path()).unwrap();
+        write_archive(file, root, &ignore, config).await.unwrap();
+
+        std::fs::read(archive_path).unwrap()
+    }
+
+    async fn compressed_bytes(compression: Compression) -> Vec<u8> {
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+
+        let config = ArchiveConfig {
+            compression,
+            ..Default::default()
+        };
+        archive_bytes(output.path(), &config).await
+    }
+
+    #[tokio::test]
+    async fn test_file_archive_names_entry() {
+        let output = tempfile::tempdir().unwrap();
+        let binary = output.path().join("handler");
+        std::fs::write(&binary, "bin").unwrap();
+
+        let archive_path = output.path().join("archive");
+        let file = tokio::fs::File::create(&archive_path).await.unwrap();
+        write_file_archive(file, &binary, "bootstrap", &ArchiveConfig::default())
+            .await
+            .unwrap();
+
+        let unpacked = tempfile::tempdir().unwrap();
+        let bytes = std::fs::read(archive_path).unwrap();
+        tokio_tar::Archive::new(bytes.as_slice())
+            .unpack(unpacked.path())
+            .await
+            .unwrap();
+
+        let names: Vec<_> = std::fs::read_dir(unpacked.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["bootstrap"]);
+    }
+
+    #[tokio::test]
+    async fn test_archive_compression() {
+        let plain = compressed_bytes(Compression::None).await;
+        assert_eq!(&plain[..9], b"bootstrap");
+
+        let gzip = compressed_bytes(Compression::Gzip).await;
+        assert_eq!(&gzip[..2], &[0x1f, 0x8b]);
+
+        let zstd = compressed_bytes(Compression::Zstd).await;
+        assert_eq!(&zstd[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+    }
+
+    fn write_output(root: &Path, modified: SystemTime) {
+        std::fs::create_dir(root.join("lib")).unwrap();
+        for path in [root.join("bootstrap"), root.join("lib").join("data")] {
+            std::fs::write(&path, "bin").unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reproducible_archive() {
+        let first = tempfile::tempdir().unwrap();
+        write_output(first.path(), SystemTime::UNIX_EPOCH);
+        let second = tempfile::tempdir().unwrap();
+        write_output(second.path(), SystemTime::now());
+
+        let config = ArchiveConfig {
+            reproducible: true,
+            ..Default::default()
+        };
+        let first_bytes = archive_bytes(first.path(), &config).await;
+        assert_eq!(first_bytes, archive_bytes(second.path(), &config).await);
+
+        let header = Header::from_byte_slice(&first_bytes[..512]);
+        assert_eq!(header.path().unwrap(), Path::new("bootstrap"));
+        assert_eq!(header.mtime().unwrap(), source_date_epoch().unwrap_or(0));
+        assert_eq!(header.uid().unwrap(), 0);
+        assert_eq!(header.gid().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_header_options() {
+        let plain = ArchiveConfig::default();
+        assert_eq!(HeaderOptions::new(&plain, None), None);
+
+        let options = HeaderOptions::new(&plain, Some(1700000000)).unwrap();
+        assert_eq!(options.mode, HeaderMode::Complete);
+        assert_eq!(options.mtime, Some(1700000000));
+
+        let reproducible = ArchiveConfig {
+            reproducible: true,
+            ..Default::default()
+        };
+        let options = HeaderOptions::new(&reproducible, None).unwrap();
+        assert_eq!(options.mode, HeaderMode::Deterministic);
+        assert_eq!(options.mtime, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_archive_umask() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let output = tempfile::tempdir().unwrap();
+        let path = output.path().join("bootstrap");
+        std::fs::write(&path, "bin").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o777)).unwrap();
+
+        let config: ArchiveConfig = toml::from_str("umask = 0o027").unwrap();
+        let bytes = archive_bytes(output.path(), &config).await;
+
+        let header = Header::from_byte_slice(&bytes[..512]);
+        assert_eq!(header.mode().unwrap() & 0o777, 0o750);
+    }
+
+    #[test]
+    fn test_manifest_summary_counts_files() {
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+
+        let mut summary = ManifestSummary::default();
+        summary.record(
+            Path::new("bootstrap"),
+            &std::fs::metadata(output.path().join("bootstrap")).unwrap(),
+        );
+        summary.record(Path::new(""), &std::fs::metadata(output.path()).unwrap());
+
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.bytes, 3);
+    }
+
+    /// Entry paths of an uncompressed tar archive, in order
+    fn entry_paths(bytes: &[u8]) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let mut offset = 0;
+        while offset + 512 <= bytes.len() {
+            let header = Header::from_byte_slice(&bytes[offset..offset + 512]);
+            if header.as_bytes().iter().all(|b| *b == 0) {
+                break;
+            }
+
+            paths.push(header.path().unwrap().into_owned());
+            let size = header.entry_size().unwrap() as usize;
+            offset += 512 + size.div_ceil(512) * 512;
+        }
+        paths
+    }
+
+    #[tokio::test]
+    async fn test_package_root_prefixes_entries() {
+        let output = tempfile::tempdir().unwrap();
+        write_output(output.path(), SystemTime::now());
+
+        let bytes = archive_bytes(output.path(), &ArchiveConfig::default()).await;
+        assert_eq!(
+            entry_paths(&bytes),
+            vec![
+                PathBuf::from("bootstrap"),
+                PathBuf::from("lib"),
+                PathBuf::from("lib/data")
+            ]
+        );
+
+        let config: ArchiveConfig = toml::from_str(r#"package_root = "app""#).unwrap();
+        let bytes = archive_bytes(output.path(), &config).await;
+        assert_eq!(
+            entry_paths(&bytes),
+            vec![
+                PathBuf::from("app"),
+                PathBuf::from("app/bootstrap"),
+                PathBuf::from("app/lib"),
+                PathBuf::from("app/lib/data")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_root_normalized() {
+        let root = |value: &str| ArchiveConfig {
+            package_root: Some(PathBuf::from(value)),
+            ..Default::default()
+        };
+
+        assert_eq!(ArchiveConfig::default().package_root().unwrap(), None);
+        assert_eq!(root(".").package_root().unwrap(), None);
+        assert_eq!(
+            root("./app/").package_root().unwrap(),
+            Some(PathBuf::from("app"))
+        );
+        assert!(root("../app").package_root().is_err());
+        assert!(root("/app").package_root().is_err());
+    }
+
+    #[test]
+    fn test_compression_config() {
+        let config: ArchiveConfig = toml::from_str(
+            r#"
+            compression = "zstd"
+            compression_level = 19
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.compression, Compression::Zstd);
+        assert_eq!(config.compression_level, Some(19));
+    }
+
+    #[test]
+    fn test_ignore_directory() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join(IGNORE_FILE), "cache/\n").unwrap();
+
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("bootstrap"), "bin").unwrap();
+        std::fs::create_dir(output.path().join("cache")).unwrap();
+        std::fs::write(output.path().join("cache").join("data"), "data").unwrap();
+
+        let ignore = load_ignore(project.path()).unwrap();
+        assert_eq!(
+            relative_paths(output.path(), &ignore),
+            vec![PathBuf::from("bootstrap")]
+        );
+    }
+}
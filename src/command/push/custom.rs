@@ -1,11 +1,16 @@
 use anyhow::{Context, bail};
 use serde::Deserialize;
-use std::{path::PathBuf, process::Stdio, time::Duration};
-use tokio::process::Command;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
 use tonic::async_trait;
 use tracing::{debug, info, warn};
 
-use super::BuildService;
+use super::env::{restrict_env, validate_pass_env};
+use super::{BuildService, compress_binary, make_executable, shell_command_with};
 
 /// Custom build configuration
 ///
@@ -13,55 +18,144 @@ use super::BuildService;
 /// Custom build scripts execute arbitrary shell commands with full system access.
 /// Only use trusted configuration files. The script runs with the same permissions
 /// as the build process.
+///
+/// The script is expected to write its executable to `$OUTPUT/$ENTRYPOINT`.
 #[derive(Debug, Deserialize)]
 pub struct CustomBuild {
     /// Shell script or command to execute
     /// The OUTPUT environment variable will contain the temp directory path
+    #[serde(default)]
     script: String,
 
+    /// Ordered commands to run instead of a single `script`
+    /// Each step runs in the same working directory and the build stops at the first failure
+    #[serde(default)]
+    steps: Vec<String>,
+
+    /// Name of the executable the script should write into $OUTPUT
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
     /// Optional timeout in seconds (default: 300 seconds / 5 minutes)
     #[serde(default = "default_timeout")]
     timeout_seconds: u64,
 
-    /// Optional working directory override
+    /// Optional working directory override, relative to the project path unless absolute
     /// If not specified, uses the project_path
     #[serde(default)]
     working_directory: Option<String>,
 
-    /// Shell to use (default: "sh" on Unix, "cmd" on Windows)
-    #[serde(default = "default_shell")]
+    /// Shell to use (default: "sh" on Unix, "cmd" on Windows); "powershell" and "pwsh" are
+    /// invoked with `-Command`
+    #[serde(default = "super::default_shell")]
     shell: String,
+
+    /// Extra environment variables passed to the script
+    /// These cannot override the reserved variables (see `RESERVED_ENV`)
+    #[serde(default)]
+    env: HashMap<String, String>,
+
+    /// Host variables forwarded to the script (besides PATH), instead of the whole
+    /// environment. Unset inherits everything
+    #[serde(default)]
+    pass_env: Option<Vec<String>>,
+
+    /// Fail instead of warning when the entrypoint is missing or not executable
+    #[serde(default)]
+    strict: bool,
+
+    /// Compress the entrypoint with `upx --best` when `upx` is installed
+    #[serde(default)]
+    compress_binary: bool,
+
+    /// Fail instead of warning when a command contains a dangerous pattern
+    #[serde(default)]
+    deny_dangerous: bool,
+
+    /// Forbidden commands checked in addition to `DANGEROUS_PATTERNS`
+    #[serde(default)]
+    dangerous_patterns: Vec<String>,
 }
 
+/// Commands that are always treated as potentially dangerous
+const DANGEROUS_PATTERNS: [&str; 4] = ["rm -rf /", "format", "del /f /s /q", "sudo"];
+
+/// Environment variables set by the CLI that user `env` entries cannot override
+const RESERVED_ENV: [&str; 4] = ["OUTPUT", "PROJECT_PATH", "TEMP_PATH", "ENTRYPOINT"];
+
 fn default_timeout() -> u64 {
     300 // 5 minutes
 }
 
-fn default_shell() -> String {
-    if cfg!(target_os = "windows") {
-        "cmd".to_string()
-    } else {
-        "sh".to_string()
-    }
+/// Whether `pattern` occurs in `command` as whole words, so `format` matches
+/// `format C:` but not `formatting` or `--format`
+fn contains_command(command: &str, pattern: &str) -> bool {
+    // Path and flag characters count as part of a word, so `rm -rf /` does not match `rm -rf /tmp`
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/');
+
+    command.match_indices(pattern).any(|(start, _)| {
+        let before = command[..start].chars().next_back();
+        let after = command[start + pattern.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
 }
 
 impl CustomBuild {
     /// Validate the custom build configuration
-    fn validate(&self) -> anyhow::Result<()> {
-        // Check script is not empty
-        if self.script.trim().is_empty() {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        // Check exactly one of script and steps is used
+        let has_script = !self.script.trim().is_empty();
+        if has_script && !self.steps.is_empty() {
+            bail!("Only one of 'script' and 'steps' can be set");
+        }
+
+        if !has_script && self.steps.is_empty() {
             bail!("Build script cannot be empty");
         }
 
-        // Warn about potentially dangerous commands
-        let dangerous_patterns = ["rm -rf /", "format", "del /f /s /q", "sudo"];
+        if let Some(index) = self.steps.iter().position(|s| s.trim().is_empty()) {
+            bail!("Build step {} cannot be empty", index + 1);
+        }
 
-        for pattern in &dangerous_patterns {
-            if self.script.contains(pattern) {
+        if self.dangerous_patterns.iter().any(|p| p.is_empty()) {
+            bail!("Dangerous patterns cannot be empty");
+        }
+
+        // Warn about, or with deny_dangerous reject, potentially dangerous commands
+        let dangerous_patterns = DANGEROUS_PATTERNS
+            .iter()
+            .copied()
+            .chain(self.dangerous_patterns.iter().map(String::as_str));
+
+        for command in self.commands() {
+            for pattern in dangerous_patterns.clone() {
+                if contains_command(command, pattern) {
+                    if self.deny_dangerous {
+                        bail!(
+                            "Build script contains forbidden command: '{}'. \
+                            Remove it or unset deny_dangerous.",
+                            pattern
+                        );
+                    }
+                    warn!(
+                        "Build script contains potentially dangerous command: '{}'. \
+                        Please review the script carefully.",
+                        pattern
+                    );
+                }
+            }
+        }
+
+        if let Some(ref pass_env) = self.pass_env {
+            validate_pass_env(pass_env)?;
+        }
+
+        // Warn about env entries that will be ignored
+        for key in self.env.keys() {
+            if RESERVED_ENV.contains(&key.as_str()) {
                 warn!(
-                    "Build script contains potentially dangerous command: '{}'. \
-                    Please review the script carefully.",
-                    pattern
+                    "Environment variable '{}' is reserved and will be ignored",
+                    key
                 );
             }
         }
@@ -83,14 +177,62 @@ impl CustomBuild {
         Ok(())
     }
 
-    /// Get the shell command arguments for the current platform
-    fn get_shell_args(&self) -> Vec<&str> {
-        if cfg!(target_os = "windows") {
-            vec!["/C"]
+    /// The commands to run, in order
+    fn commands(&self) -> Vec<&str> {
+        if self.steps.is_empty() {
+            vec![self.script.as_str()]
         } else {
-            vec!["-c"]
+            self.steps.iter().map(String::as_str).collect()
         }
     }
+
+    /// Run a single command through the configured shell
+    async fn run_command(
+        &self,
+        command: &str,
+        working_dir: &Path,
+        temp_path: &Path,
+    ) -> anyhow::Result<()> {
+        let mut cmd = shell_command_with(&self.shell, command);
+
+        // Host allowlist, then user variables, so the reserved ones below always win
+        restrict_env(&mut cmd, self.pass_env.as_deref());
+        cmd.envs(&self.env);
+
+        cmd.current_dir(working_dir)
+            .env("OUTPUT", temp_path)
+            .env("PROJECT_PATH", working_dir)
+            .env("TEMP_PATH", temp_path)
+            .env("ENTRYPOINT", &self.entrypoint)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true); // Ensure child is killed if this future is dropped
+
+        let child = cmd.spawn().with_context(|| {
+            format!("Failed to spawn build script using shell '{}'", self.shell)
+        })?;
+
+        let status = child
+            .wait_with_output()
+            .await
+            .with_context(|| "Failed to wait for build script completion")?
+            .status;
+
+        if !status.success() {
+            let code = status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            bail!(
+                "Build script failed with exit code: {}. \
+                Check the script output above for details.",
+                code
+            );
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -101,8 +243,11 @@ impl BuildService for CustomBuild {
             .context("Invalid custom build configuration")?;
 
         info!("Starting custom build script");
-        debug!("Script: {}", self.script);
+        for command in self.commands() {
+            debug!("Script: {}", command);
+        }
         debug!("Timeout: {}s", self.timeout_seconds);
+        debug!("Entrypoint: {}", self.entrypoint);
 
         // Validate paths
         if !project_path.exists() {
@@ -116,7 +261,12 @@ impl BuildService for CustomBuild {
 
         // Determine working directory
         let working_dir = if let Some(ref wd) = self.working_directory {
-            let custom_wd = project_path.join(wd);
+            let wd = Path::new(wd);
+            let custom_wd = if wd.is_absolute() {
+                wd.to_path_buf()
+            } else {
+                project_path.join(wd)
+            };
             if !custom_wd.exists() {
                 bail!("Custom working directory does not exist: {:?}", custom_wd);
             }
@@ -128,53 +278,33 @@ impl BuildService for CustomBuild {
         debug!("Working directory: {:?}", working_dir);
         debug!("Output directory (OUTPUT env): {:?}", temp_path);
 
-        // Build command
-        let mut cmd = Command::new(&self.shell);
-
-        for arg in self.get_shell_args() {
-            cmd.arg(arg);
-        }
-
-        cmd.arg(&self.script)
-            .current_dir(&working_dir)
-            .env("OUTPUT", &temp_path)
-            .env("PROJECT_PATH", &working_dir)
-            .env("TEMP_PATH", &temp_path)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .kill_on_drop(true); // Ensure child is killed if this future is dropped
-
-        // Execute with timeout
-        let child = cmd.spawn().with_context(|| {
-            format!("Failed to spawn build script using shell '{}'", self.shell)
-        })?;
-
+        // Run all commands with the timeout applied to the whole sequence
+        let commands = self.commands();
         let timeout = Duration::from_secs(self.timeout_seconds);
 
-        let status = tokio::time::timeout(timeout, child.wait_with_output())
-            .await
-            .with_context(|| {
-                format!(
-                    "Build script timed out after {} seconds. \
-                    Consider increasing the timeout or optimizing your build.",
-                    self.timeout_seconds
-                )
-            })?
-            .with_context(|| "Failed to wait for build script completion")?
-            .status;
-
-        if !status.success() {
-            let code = status
-                .code()
-                .map(|c| c.to_string())
-                .unwrap_or_else(|| "unknown".to_string());
+        tokio::time::timeout(timeout, async {
+            for (index, command) in commands.iter().enumerate() {
+                if commands.len() == 1 {
+                    self.run_command(command, &working_dir, &temp_path).await?;
+                    continue;
+                }
+
+                info!("Running build step {}/{}", index + 1, commands.len());
+                self.run_command(command, &working_dir, &temp_path)
+                    .await
+                    .with_context(|| format!("Build step {} failed", index + 1))?;
+            }
 
-            bail!(
-                "Build script failed with exit code: {}. \
-                Check the script output above for details.",
-                code
-            );
-        }
+            anyhow::Ok(())
+        })
+        .await
+        .with_context(|| {
+            format!(
+                "Build script timed out after {} seconds. \
+                Consider increasing the timeout or optimizing your build.",
+                self.timeout_seconds
+            )
+        })??;
 
         info!("Custom build script completed successfully");
 
@@ -194,22 +324,85 @@ impl BuildService for CustomBuild {
             );
         }
 
+        self.check_entrypoint(&temp_path).await?;
+
+        let entrypoint_path = temp_path.join(&self.entrypoint);
+        if self.compress_binary && entrypoint_path.is_file() {
+            compress_binary(&entrypoint_path).await?;
+        }
+
         Ok(())
     }
 }
 
+impl CustomBuild {
+    /// Check the script produced the entrypoint and that the worker can execute it,
+    /// fixing the permissions unless `strict` is set
+    async fn check_entrypoint(&self, temp_path: &Path) -> anyhow::Result<()> {
+        let entrypoint_path = temp_path.join(&self.entrypoint);
+
+        if !entrypoint_path.is_file() {
+            let message = format!(
+                "Entrypoint '{}' was not found in $OUTPUT. \
+                Make sure your script writes it to $OUTPUT/$ENTRYPOINT",
+                self.entrypoint
+            );
+            if self.strict {
+                bail!(message);
+            }
+            warn!("{}", message);
+            return Ok(());
+        }
+
+        if !is_executable(&entrypoint_path)? {
+            if self.strict {
+                bail!(
+                    "Entrypoint '{}' is not executable, did you forget to chmod +x it?",
+                    self.entrypoint
+                );
+            }
+            warn!(
+                "Entrypoint '{}' is not executable, marking it executable",
+                self.entrypoint
+            );
+        }
+
+        // Ensure the worker can execute the entrypoint
+        make_executable(&entrypoint_path).await
+    }
+}
+
+/// Whether the file has any execute bit set; always true on Windows
+fn is_executable(path: &Path) -> anyhow::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+        Ok(metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(true)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A build running `script` with every other setting at its default
+    fn script_build(script: &str) -> CustomBuild {
+        let script = toml::Value::String(script.to_string());
+        toml::from_str(&format!("script = {}\ntimeout_seconds = 10", script)).unwrap()
+    }
+
     #[test]
     fn test_validate_empty_script() {
-        let build = CustomBuild {
-            script: "   ".to_string(),
-            timeout_seconds: 300,
-            working_directory: None,
-            shell: default_shell(),
-        };
+        let build = script_build("   ");
 
         assert!(build.validate().is_err());
     }
@@ -217,10 +410,8 @@ mod tests {
     #[test]
     fn test_validate_zero_timeout() {
         let build = CustomBuild {
-            script: "echo test".to_string(),
             timeout_seconds: 0,
-            working_directory: None,
-            shell: default_shell(),
+            ..script_build("echo test")
         };
 
         assert!(build.validate().is_err());
@@ -228,40 +419,259 @@ mod tests {
 
     #[test]
     fn test_validate_valid_config() {
+        let build = script_build("echo 'Building...'");
+
+        assert!(build.validate().is_ok());
+    }
+
+    #[test]
+    fn test_deny_dangerous() {
+        let build = script_build("sudo make install");
+        assert!(build.validate().is_ok());
+
         let build = CustomBuild {
-            script: "echo 'Building...'".to_string(),
-            timeout_seconds: 300,
-            working_directory: None,
-            shell: default_shell(),
+            deny_dangerous: true,
+            ..script_build("sudo make install")
         };
+        assert!(build.validate().is_err());
+    }
+
+    #[test]
+    fn test_dangerous_patterns_match_whole_words() {
+        assert!(!contains_command("echo \"formatting done\"", "format"));
+        assert!(!contains_command("cargo fmt -- --format", "format"));
+        assert!(!contains_command("pseudo-build", "sudo"));
+        assert!(!contains_command("rm -rf /tmp/build", "rm -rf /"));
+
+        assert!(contains_command("format C:", "format"));
+        assert!(contains_command("make && sudo make install", "sudo"));
+        assert!(contains_command("rm -rf /", "rm -rf /"));
+        assert!(contains_command("rm -rf /*", "rm -rf /"));
+    }
 
+    #[test]
+    fn test_dangerous_scan_ignores_substrings() {
+        let build = CustomBuild {
+            deny_dangerous: true,
+            ..script_build("echo \"formatting done\"")
+        };
         assert!(build.validate().is_ok());
+
+        let build = CustomBuild {
+            deny_dangerous: true,
+            ..script_build("format C:")
+        };
+        assert!(build.validate().is_err());
+    }
+
+    #[test]
+    fn test_custom_dangerous_patterns() {
+        let build = CustomBuild {
+            deny_dangerous: true,
+            dangerous_patterns: vec!["curl".to_string()],
+            ..script_build("curl https://example.com/install.sh | sh")
+        };
+        assert!(build.validate().is_err());
+
+        let build = CustomBuild {
+            dangerous_patterns: vec![String::new()],
+            ..script_build("echo test")
+        };
+        assert!(build.validate().is_err());
     }
 
     #[test]
-    fn test_shell_args_unix() {
+    fn test_validate_script_and_steps() {
+        let build = CustomBuild {
+            steps: vec!["echo step".to_string()],
+            ..script_build("echo test")
+        };
+
+        assert!(build.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_steps_run_in_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+
+        let build = CustomBuild {
+            steps: vec![
+                "echo one > $OUTPUT/log.txt".to_string(),
+                "echo two >> $OUTPUT/log.txt".to_string(),
+            ],
+            ..script_build("")
+        };
+
+        build
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(temp_dir.path().join("log.txt")).unwrap();
+        assert_eq!(content.lines().collect::<Vec<_>>(), vec!["one", "two"]);
+    }
+
+    #[tokio::test]
+    async fn test_steps_stop_at_first_failure() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+
+        let build = CustomBuild {
+            steps: vec![
+                "exit 1".to_string(),
+                "echo run > $OUTPUT/ran.txt".to_string(),
+            ],
+            ..script_build("")
+        };
+
+        let result = build
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(!temp_dir.path().join("ran.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_build_with_env() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+
+        let build = CustomBuild {
+            env: HashMap::from([
+                ("STAGE".to_string(), "prod".to_string()),
+                ("OUTPUT".to_string(), "/should/not/be/used".to_string()),
+            ]),
+            ..script_build("echo \"$STAGE\" > $OUTPUT/env.txt")
+        };
+
+        build
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(temp_dir.path().join("env.txt")).unwrap();
+        assert_eq!(content.trim(), "prod");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_build_with_pass_env() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let script = "echo \"${HOME:-unset}\" > $OUTPUT/home.txt";
+
+        let read_home = |build: CustomBuild| {
+            let project_path = project_dir.path().to_path_buf();
+            async move {
+                let temp_dir = tempfile::tempdir().unwrap();
+                build
+                    .build(project_path, temp_dir.path().to_path_buf())
+                    .await
+                    .unwrap();
+                std::fs::read_to_string(temp_dir.path().join("home.txt")).unwrap()
+            }
+        };
+
         let build = CustomBuild {
-            script: "test".to_string(),
-            timeout_seconds: 300,
-            working_directory: None,
-            shell: "sh".to_string(),
+            pass_env: Some(Vec::new()),
+            ..script_build(script)
         };
+        assert_eq!(read_home(build).await.trim(), "unset");
+
+        if std::env::var_os("HOME").is_some() {
+            let build = CustomBuild {
+                pass_env: Some(vec!["HOME".to_string()]),
+                ..script_build(script)
+            };
+            assert_ne!(read_home(build).await.trim(), "unset");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_with_absolute_working_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        let work_dir = tempfile::tempdir().unwrap();
+
+        let build = CustomBuild {
+            working_directory: Some(work_dir.path().to_str().unwrap().to_string()),
+            ..script_build("pwd > $OUTPUT/pwd.txt")
+        };
+
+        build
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+
+        let pwd = std::fs::read_to_string(temp_dir.path().join("pwd.txt")).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(pwd.trim()).unwrap(),
+            std::fs::canonicalize(work_dir.path()).unwrap()
+        );
+    }
 
-        if !cfg!(target_os = "windows") {
-            assert_eq!(build.get_shell_args(), vec!["-c"]);
+    #[tokio::test]
+    async fn test_missing_entrypoint() {
+        let project_dir = tempfile::tempdir().unwrap();
+
+        for (strict, expect_ok) in [(false, true), (true, false)] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let build = CustomBuild {
+                strict,
+                ..script_build("echo log > $OUTPUT/build.log")
+            };
+            let result = build
+                .build(
+                    project_dir.path().to_path_buf(),
+                    temp_dir.path().to_path_buf(),
+                )
+                .await;
+
+            assert_eq!(result.is_ok(), expect_ok);
         }
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_strict_rejects_non_executable_entrypoint() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+
+        let build = CustomBuild {
+            strict: true,
+            ..script_build("echo bin > $OUTPUT/$ENTRYPOINT")
+        };
+        let result = build
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_simple_build() {
         let temp_dir = tempfile::tempdir().unwrap();
         let project_dir = tempfile::tempdir().unwrap();
 
         let build = CustomBuild {
-            script: "echo 'test content' > $OUTPUT/test.txt".to_string(),
-            timeout_seconds: 10,
-            working_directory: None,
-            shell: default_shell(),
+            entrypoint: "test.txt".to_string(),
+            ..script_build("echo 'test content' > $OUTPUT/$ENTRYPOINT")
         };
 
         let result = build
@@ -275,5 +685,16 @@ mod tests {
 
         let output_file = temp_dir.path().join("test.txt");
         assert!(output_file.exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&output_file)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
     }
 }
@@ -0,0 +1,264 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tokio::process::Command;
+use tonic::async_trait;
+use tracing::{debug, info, warn};
+
+use super::{BuildService, make_executable, require_tool, run_with_timeout};
+
+/// Build an image from a Dockerfile and copy the artifact out of it
+#[derive(Debug, Deserialize)]
+pub struct DockerBuildConfig {
+    /// Dockerfile to build, relative to the project path
+    #[serde(default = "default_dockerfile")]
+    dockerfile: PathBuf,
+
+    /// Build context, relative to the project path
+    #[serde(default = "default_context")]
+    context: PathBuf,
+
+    /// Values passed to the build as `--build-arg KEY=VALUE`
+    #[serde(default)]
+    build_args: BTreeMap<String, String>,
+
+    /// Absolute path of the artifact inside the built image
+    artifact: String,
+
+    /// Name the artifact is copied to in the output
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Optional timeout in seconds for the image build (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+}
+
+fn default_dockerfile() -> PathBuf {
+    PathBuf::from("Dockerfile")
+}
+
+fn default_context() -> PathBuf {
+    PathBuf::from(".")
+}
+
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+impl DockerBuildConfig {
+    /// Validate the configuration and that the Dockerfile exists
+    pub(super) fn validate(&self, project_path: &Path) -> anyhow::Result<PathBuf> {
+        if self.artifact.trim().is_empty() {
+            bail!("Docker build artifact cannot be empty");
+        }
+
+        if !self.artifact.starts_with('/') {
+            bail!(
+                "Docker build artifact must be an absolute path in the image, got '{}'",
+                self.artifact
+            );
+        }
+
+        if self.timeout_seconds == 0 {
+            bail!("Timeout must be greater than 0");
+        }
+
+        let dockerfile = project_path.join(&self.dockerfile);
+        if !dockerfile.is_file() {
+            bail!("No Dockerfile found at {:?}", dockerfile);
+        }
+
+        Ok(dockerfile)
+    }
+
+    /// Arguments for `docker build`, writing the image id to `iidfile`
+    fn build_command_args(&self, project_path: &Path, iidfile: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "build".into(),
+            "--file".into(),
+            project_path.join(&self.dockerfile).into(),
+            "--iidfile".into(),
+            iidfile.into(),
+        ];
+
+        for (key, value) in &self.build_args {
+            args.push("--build-arg".into());
+            args.push(format!("{}={}", key, value).into());
+        }
+
+        args.push(project_path.join(&self.context).into());
+        args
+    }
+
+    /// Run `docker build` and return the id of the built image
+    async fn build_image(&self, project_path: &Path) -> anyhow::Result<String> {
+        let iidfile = tempfile::NamedTempFile::new().context("Failed to create image id file")?;
+
+        let mut cmd = Command::new("docker");
+        cmd.args(self.build_command_args(project_path, iidfile.path()))
+            .current_dir(project_path);
+        run_with_timeout(cmd, "docker build", self.timeout_seconds).await?;
+
+        let image = tokio::fs::read_to_string(iidfile.path())
+            .await
+            .context("Failed to read built image id")?;
+        let image = image.trim().to_string();
+        if image.is_empty() {
+            bail!("docker build did not report an image id");
+        }
+
+        Ok(image)
+    }
+
+    /// Copy the artifact out of `image` into `output_path`
+    async fn copy_artifact(&self, image: &str, output_path: &Path) -> anyhow::Result<()> {
+        // Artifact-only images often have no CMD, so give one; the container never runs
+        let container = docker_output(&["create", image, "/nonexistent"])
+            .await
+            .context("Failed to create container from built image")?;
+        debug!("Created container {}", container);
+
+        let source = format!("{}:{}", container, self.artifact);
+        let copied = Command::new("docker")
+            .arg("cp")
+            .arg(&source)
+            .arg(output_path)
+            .status()
+            .await
+            .context("Failed to execute docker cp");
+
+        // Always remove the container, even if the copy failed
+        if let Err(e) = docker_output(&["rm", &container]).await {
+            warn!("Failed to remove container {}: {:#}", container, e);
+        }
+
+        let status = copied?;
+        if !status.success() {
+            bail!(
+                "Failed to copy '{}' out of the image, check the artifact path",
+                self.artifact
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a docker command and return its trimmed stdout
+async fn docker_output(args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("Failed to execute docker {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        bail!(
+            "docker {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[async_trait]
+impl BuildService for DockerBuildConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        let dockerfile = self
+            .validate(&project_path)
+            .context("Invalid docker build configuration")?;
+        require_tool("docker", &["--version"]).await?;
+
+        info!("Building image from {:?}", dockerfile);
+        let image = self.build_image(&project_path).await?;
+        debug!("Built image {}", image);
+
+        let output_path = temp_path.join(&self.entrypoint);
+        info!(
+            "Copying '{}' from the image as '{}'",
+            self.artifact, self.entrypoint
+        );
+        let copied = self.copy_artifact(&image, &output_path).await;
+
+        // The image is only needed for the copy, so don't leave it behind
+        if let Err(e) = docker_output(&["rmi", &image]).await {
+            warn!("Failed to remove image {}: {:#}", image, e);
+        }
+        copied?;
+
+        if !output_path.is_file() {
+            bail!("Docker build artifact '{}' is not a file", self.artifact);
+        }
+
+        make_executable(&output_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: &str) -> DockerBuildConfig {
+        toml::from_str(&format!("artifact = \"/app/handler\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = config("");
+        assert_eq!(config.dockerfile, PathBuf::from("Dockerfile"));
+        assert_eq!(config.context, PathBuf::from("."));
+        assert_eq!(config.entrypoint, "bootstrap");
+        assert_eq!(config.timeout_seconds, 900);
+    }
+
+    #[test]
+    fn test_build_command_args() {
+        let config = config(
+            "dockerfile = \"docker/Dockerfile\"\n\
+             [build_args]\n\
+             VERSION = \"1.2\"\n\
+             FEATURES = \"full\"",
+        );
+
+        let args = config.build_command_args(Path::new("/project"), Path::new("/tmp/iid"));
+        let expected: Vec<OsString> = vec![
+            "build".into(),
+            "--file".into(),
+            "/project/docker/Dockerfile".into(),
+            "--iidfile".into(),
+            "/tmp/iid".into(),
+            "--build-arg".into(),
+            "FEATURES=full".into(),
+            "--build-arg".into(),
+            "VERSION=1.2".into(),
+            "/project/.".into(),
+        ];
+        assert_eq!(args, expected);
+    }
+
+    #[test]
+    fn test_validate_requires_dockerfile() {
+        let project_dir = tempfile::tempdir().unwrap();
+        assert!(config("").validate(project_dir.path()).is_err());
+
+        std::fs::write(project_dir.path().join("Dockerfile"), "FROM scratch").unwrap();
+        assert!(config("").validate(project_dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_relative_artifact() {
+        let project_dir = tempfile::tempdir().unwrap();
+        std::fs::write(project_dir.path().join("Dockerfile"), "FROM scratch").unwrap();
+
+        let config: DockerBuildConfig = toml::from_str("artifact = \"handler\"").unwrap();
+        assert!(config.validate(project_dir.path()).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{Result, bail};
+use tokio::process::Command;
+
+/// Host variables forwarded even with a `pass_env` allowlist, so build tools can be found
+const ALWAYS_PASSED: [&str; 1] = ["PATH"];
+
+/// Expand `${VAR}` and `${VAR:-default}` references using the process environment.
+/// `$${VAR}` is left as a literal `${VAR}`, e.g. for shell variables in build scripts.
+pub fn expand_env(input: &str) -> Result<String> {
+    expand_with(input, |name| std::env::var(name).ok())
+}
+
+/// Expand variable references using the given lookup, failing on unset variables
+/// that have no default
+fn expand_with(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    expand_vars(
+        input,
+        lookup,
+        "Config references unset environment variables",
+    )
+}
+
+/// Expand `${VAR}` references with `lookup` like [`expand_env`], failing with
+/// `unset_message` followed by every unresolved name
+pub fn expand_vars(
+    input: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    unset_message: &str,
+) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut missing = Vec::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            bail!("Unterminated variable reference: '{}'", &rest[start..]);
+        };
+
+        let reference = &after[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        if name.is_empty() {
+            bail!("Empty variable reference: '${{{}}}'", reference);
+        }
+
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => output.push_str(default),
+            (Some(value), _) => output.push_str(&value),
+            (None, Some(default)) => output.push_str(default),
+            (None, None) if missing.iter().any(|m| m == name) => {}
+            (None, None) => missing.push(name.to_string()),
+        }
+
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+
+    if !missing.is_empty() {
+        bail!("{}: {}", unset_message, missing.join(", "));
+    }
+
+    Ok(output)
+}
+
+/// Limit `cmd` to the host variables named in `pass_env`, or leave it inheriting the
+/// whole environment when unset. Variables set on `cmd` afterwards are unaffected.
+pub fn restrict_env(cmd: &mut Command, pass_env: Option<&[String]>) {
+    let Some(pass_env) = pass_env else {
+        return;
+    };
+
+    cmd.env_clear();
+    let names = ALWAYS_PASSED
+        .iter()
+        .copied()
+        .chain(pass_env.iter().map(String::as_str));
+    for name in names {
+        if let Some(value) = std::env::var_os(name) {
+            cmd.env(name, value);
+        }
+    }
+}
+
+/// Check every `pass_env` entry is a usable variable name
+pub fn validate_pass_env(pass_env: &[String]) -> Result<()> {
+    if let Some(name) = pass_env
+        .iter()
+        .find(|name| name.is_empty() || name.contains('='))
+    {
+        bail!("Invalid pass_env variable name '{}'", name);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "REGISTRY" => Some("http://registry:50001".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_set_variable() {
+        let result = expand_with(r#"registry_url = "${REGISTRY}""#, lookup).unwrap();
+        assert_eq!(result, r#"registry_url = "http://registry:50001""#);
+    }
+
+    #[test]
+    fn test_expand_unset_variable() {
+        let err = expand_with("a = \"${FOO}\"\nb = \"${BAR}\"", lookup).unwrap_err();
+        assert!(err.to_string().contains("FOO, BAR"));
+    }
+
+    #[test]
+    fn test_expand_unset_variable_listed_once() {
+        let err = expand_vars("${FOO} ${FOO}", lookup, "Unresolved").unwrap_err();
+        assert_eq!(err.to_string(), "Unresolved: FOO");
+    }
+
+    #[test]
+    fn test_expand_default() {
+        let result = expand_with("${MISSING:-http://localhost:50001}", lookup).unwrap();
+        assert_eq!(result, "http://localhost:50001");
+
+        let result = expand_with("${EMPTY:-fallback}", lookup).unwrap();
+        assert_eq!(result, "fallback");
+
+        let result = expand_with("${REGISTRY:-http://localhost:50001}", lookup).unwrap();
+        assert_eq!(result, "http://registry:50001");
+    }
+
+    #[test]
+    fn test_expand_without_references() {
+        let input = "script = \"echo $OUTPUT\"";
+        assert_eq!(expand_with(input, lookup).unwrap(), input);
+    }
+
+    #[test]
+    fn test_expand_escaped() {
+        let result = expand_with("cp bootstrap $${OUTPUT}/${MISSING:-bin}", lookup).unwrap();
+        assert_eq!(result, "cp bootstrap ${OUTPUT}/bin");
+    }
+
+    #[test]
+    fn test_expand_unterminated() {
+        assert!(expand_with("${REGISTRY", lookup).is_err());
+    }
+
+    #[test]
+    fn test_restrict_env() {
+        let mut cmd = Command::new("true");
+        restrict_env(&mut cmd, None);
+        assert_eq!(cmd.as_std().get_envs().count(), 0);
+
+        let mut cmd = Command::new("true");
+        restrict_env(&mut cmd, Some(&["NOCTI_UNSET_TEST_VAR".to_string()]));
+        let names: Vec<_> = cmd.as_std().get_envs().map(|(name, _)| name).collect();
+        let expected: Vec<&std::ffi::OsStr> = match std::env::var_os("PATH") {
+            Some(_) => vec!["PATH".as_ref()],
+            None => Vec::new(),
+        };
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_validate_pass_env() {
+        assert!(validate_pass_env(&["HOME".to_string()]).is_ok());
+        assert!(validate_pass_env(&[String::new()]).is_err());
+        assert!(validate_pass_env(&["A=B".to_string()]).is_err());
+    }
+}
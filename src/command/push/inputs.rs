@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use super::Build;
+use super::archive;
+
+/// Prefix of the tag recording the inputs of a push
+pub(super) const INPUT_TAG_PREFIX: &str = "inputs-";
+
+/// Hex digits of the input hash kept in the tag
+const INPUT_TAG_DIGITS: usize = 16;
+
+/// Tag identifying the build inputs, recorded by `push --if-changed` and compared with
+/// the tag registered for the key on the next push. `selection` holds command line
+/// choices that change the build without changing any file
+pub(super) fn input_tag(
+    project_path: &Path,
+    build: &Build,
+    config_path: Option<&Path>,
+    selection: &[String],
+) -> Result<String> {
+    let files = input_files(project_path, build, config_path)?;
+    debug!("Hashing {} input files", files.len());
+
+    let hash = hash_inputs(&files, selection)?;
+    Ok(format!("{}{}", INPUT_TAG_PREFIX, &hash[..INPUT_TAG_DIGITS]))
+}
+
+/// Files the build reads, as (path, stable name) pairs: every file below the project not
+/// ignored by `.noctiignore`, except `.git` and a Rust build's `target`, plus the
+/// `--config` file and the workspace `Cargo.lock` of a Rust workspace member
+fn input_files(
+    project_path: &Path,
+    build: &Build,
+    config_path: Option<&Path>,
+) -> Result<Vec<(PathBuf, String)>> {
+    let is_rust = matches!(build, Build::Rust(_));
+    let skipped: &[&str] = if is_rust {
+        &["/.git/", "/target/"]
+    } else {
+        &["/.git/"]
+    };
+    let ignore = archive::load_ignore_with(project_path, skipped)?;
+
+    let mut files = Vec::new();
+    for (path, relative) in archive::collect_entries(project_path, &ignore)? {
+        if path.is_file() {
+            files.push((path, stable_name(&relative)));
+        }
+    }
+
+    if let Some(config_path) = config_path {
+        let name = config_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        files.push((config_path.to_path_buf(), format!("<config>/{}", name)));
+    }
+
+    let lock = if is_rust && !project_path.join("Cargo.lock").is_file() {
+        workspace_lock(project_path)?
+    } else {
+        None
+    };
+    if let Some(lock) = lock {
+        debug!("Hashing workspace lock file {:?}", lock);
+        files.push((lock, "<workspace>/Cargo.lock".to_string()));
+    }
+
+    Ok(files)
+}
+
+/// `Cargo.lock` in the closest directory above the project, where a workspace keeps it
+fn workspace_lock(project_path: &Path) -> Result<Option<PathBuf>> {
+    let project_path = std::fs::canonicalize(project_path)
+        .with_context(|| format!("Failed to resolve {:?}", project_path))?;
+
+    Ok(project_path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| lock.is_file()))
+}
+
+/// Relative path with `/` separators, so the hash does not depend on the platform
+fn stable_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// SHA256 over every file's name and content in order, followed by `selection`
+fn hash_inputs(files: &[(PathBuf, String)], selection: &[String]) -> Result<String> {
+    let mut hasher = Sha256::new();
+
+    for (path, name) in files {
+        let content =
+            std::fs::read(path).with_context(|| format!("Failed to read input {:?}", path))?;
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+
+    // A new CLI version may archive the same build differently
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    for value in selection {
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn rust_build() -> Build {
+        toml::from_str("type = \"rust\"").unwrap()
+    }
+
+    fn tag(root: &Path) -> String {
+        input_tag(root, &rust_build(), None, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_input_tag_tracks_sources() {
+        let project = tempfile::tempdir().unwrap();
+        write(project.path(), "Cargo.toml", "[package]");
+        write(project.path(), "src/main.rs", "fn main() {}");
+
+        let first = tag(project.path());
+        assert!(first.starts_with(INPUT_TAG_PREFIX));
+        assert_eq!(first.len(), INPUT_TAG_PREFIX.len() + INPUT_TAG_DIGITS);
+        assert_eq!(first, tag(project.path()));
+
+        write(project.path(), "src/main.rs", "fn main() { println!() }");
+        assert_ne!(first, tag(project.path()));
+    }
+
+    #[test]
+    fn test_input_tag_skips_outputs_and_ignored() {
+        let project = tempfile::tempdir().unwrap();
+        write(project.path(), "src/main.rs", "fn main() {}");
+        write(project.path(), ".noctiignore", "*.log\n");
+        let first = tag(project.path());
+
+        write(project.path(), "target/release/app", "bin");
+        write(project.path(), ".git/HEAD", "ref");
+        write(project.path(), "build.log", "log");
+        assert_eq!(first, tag(project.path()));
+
+        // Only a Rust build's target directory is skipped
+        let custom: Build = toml::from_str("type = \"custom\"\nscript = \"make\"").unwrap();
+        let custom_tag = input_tag(project.path(), &custom, None, &[]).unwrap();
+        write(project.path(), "target/release/app", "changed");
+        assert_ne!(
+            custom_tag,
+            input_tag(project.path(), &custom, None, &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_input_tag_includes_selection_and_workspace_lock() {
+        let workspace = tempfile::tempdir().unwrap();
+        write(workspace.path(), "Cargo.lock", "version = 3");
+        write(workspace.path(), "api/src/main.rs", "fn main() {}");
+        let project = workspace.path().join("api");
+
+        let first = tag(&project);
+        let selected = input_tag(&project, &rust_build(), None, &["api".to_string()]).unwrap();
+        assert_ne!(first, selected);
+
+        write(workspace.path(), "Cargo.lock", "version = 4");
+        assert_ne!(first, tag(&project));
+    }
+
+    #[test]
+    fn test_stable_name() {
+        assert_eq!(
+            stable_name(&Path::new("src").join("bin").join("api.rs")),
+            "src/bin/api.rs"
+        );
+    }
+}
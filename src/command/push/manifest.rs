@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use tracing::{debug, info};
+
+use super::{BuildArtifact, BuildOutput};
+
+/// Name of the provenance file added to the artifact
+pub(super) const MANIFEST_FILE: &str = "manifest.json";
+
+/// Provenance written into the artifact with `include_manifest = true`
+#[derive(Debug, Serialize)]
+pub(super) struct ArtifactManifest {
+    /// Key the artifact is pushed under
+    pub(super) name: String,
+    /// Build type from the config, e.g. "rust"
+    pub(super) build_type: &'static str,
+    /// Commit checked out in the project, if it is in a git repository
+    pub(super) git_commit: Option<String>,
+    /// Unix time of the build, `SOURCE_DATE_EPOCH` when set
+    pub(super) timestamp: u64,
+    pub(super) cli_version: &'static str,
+}
+
+impl ArtifactManifest {
+    pub(super) fn new(name: String, build_type: &'static str, git_commit: Option<String>) -> Self {
+        Self {
+            name,
+            build_type,
+            git_commit,
+            timestamp: build_timestamp(),
+            cli_version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, taken from `SOURCE_DATE_EPOCH` for reproducible builds
+fn build_timestamp() -> u64 {
+    if let Some(epoch) = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+    {
+        return epoch;
+    }
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Write `manifest` into the artifact. A single file output is first moved into the
+/// build directory, next to the manifest
+pub(super) async fn add_manifest(
+    artifact: &mut BuildArtifact,
+    manifest: &ArtifactManifest,
+) -> Result<()> {
+    if let BuildOutput::File { ref path, ref name } = artifact.output {
+        let dir = artifact.temp_dir.path().to_path_buf();
+        link_or_copy(path, &dir.join(name)).await?;
+        artifact.output = BuildOutput::Directory(dir);
+    }
+
+    let BuildOutput::Directory(ref dir) = artifact.output else {
+        unreachable!("single file output was moved into the build directory");
+    };
+    let path = dir.join(MANIFEST_FILE);
+    if path.exists() {
+        bail!(
+            "Build output already contains {}, rename it or disable include_manifest",
+            MANIFEST_FILE
+        );
+    }
+
+    let content =
+        serde_json::to_vec_pretty(manifest).context("Failed to serialize artifact manifest")?;
+    tokio::fs::write(&path, content)
+        .await
+        .with_context(|| format!("Failed to write {:?}", path))?;
+
+    info!(commit = ?manifest.git_commit, "Added {} to the artifact", MANIFEST_FILE);
+    Ok(())
+}
+
+/// Hard link `source` to `destination`, copying when linking is not possible
+async fn link_or_copy(source: &Path, destination: &Path) -> Result<()> {
+    match tokio::fs::hard_link(source, destination).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            debug!("Could not hard link {:?} ({}), copying it", source, e);
+            tokio::fs::copy(source, destination)
+                .await
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, destination))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::push::archive::{self, ArchiveConfig};
+
+    fn output_artifact(output: impl FnOnce(&Path) -> BuildOutput) -> BuildArtifact {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = output(temp_dir.path());
+        BuildArtifact { temp_dir, output }
+    }
+
+    fn manifest() -> ArtifactManifest {
+        ArtifactManifest {
+            name: "hello".to_string(),
+            build_type: "rust",
+            git_commit: Some("0123abc".to_string()),
+            timestamp: 1700000000,
+            cli_version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+
+    async fn unpacked_manifest(artifact: &BuildArtifact) -> serde_json::Value {
+        let BuildOutput::Directory(ref root) = artifact.output else {
+            panic!("manifest output should be a directory");
+        };
+        let project = tempfile::tempdir().unwrap();
+        let ignore = archive::load_ignore(project.path()).unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive_path = archive_dir.path().join("archive");
+        let file = tokio::fs::File::create(&archive_path).await.unwrap();
+        archive::write_archive(file, root, &ignore, &ArchiveConfig::default())
+            .await
+            .unwrap();
+
+        let unpacked = tempfile::tempdir().unwrap();
+        let bytes = std::fs::read(archive_path).unwrap();
+        tokio_tar::Archive::new(bytes.as_slice())
+            .unpack(unpacked.path())
+            .await
+            .unwrap();
+        assert!(unpacked.path().join("bootstrap").is_file());
+
+        let content = std::fs::read(unpacked.path().join(MANIFEST_FILE)).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_manifest_in_directory_archive() {
+        let mut artifact = output_artifact(|dir| {
+            std::fs::write(dir.join("bootstrap"), "bin").unwrap();
+            BuildOutput::Directory(dir.to_path_buf())
+        });
+        add_manifest(&mut artifact, &manifest()).await.unwrap();
+
+        let value = unpacked_manifest(&artifact).await;
+        assert_eq!(value["name"], "hello");
+        assert_eq!(value["build_type"], "rust");
+        assert_eq!(value["git_commit"], "0123abc");
+        assert_eq!(value["timestamp"], 1700000000);
+        assert_eq!(value["cli_version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_moves_single_file() {
+        let target = tempfile::tempdir().unwrap();
+        let binary = target.path().join("handler");
+        std::fs::write(&binary, "bin").unwrap();
+
+        let mut artifact = output_artifact(|_| BuildOutput::File {
+            path: binary.clone(),
+            name: "bootstrap".to_string(),
+        });
+        add_manifest(&mut artifact, &manifest()).await.unwrap();
+
+        let value = unpacked_manifest(&artifact).await;
+        assert_eq!(value["name"], "hello");
+    }
+
+    #[tokio::test]
+    async fn test_manifest_does_not_overwrite_output() {
+        let mut artifact = output_artifact(|dir| {
+            std::fs::write(dir.join(MANIFEST_FILE), "{}").unwrap();
+            BuildOutput::Directory(dir.to_path_buf())
+        });
+        assert!(add_manifest(&mut artifact, &manifest()).await.is_err());
+    }
+}
@@ -1,69 +1,421 @@
+use std::io::IsTerminal;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
 use custom::CustomBuild;
-use crate::api::{
-    controlplane::{
-        SetDigestToNameRequest, control_plane_service_client::ControlPlaneServiceClient,
-    },
-    registry::{self, RegistryPushRequest},
-};
+use crate::api::registry::{self, RegistryPushRequest};
 use registry::registry_service_client::RegistryServiceClient;
-use rust::RustBuild;
+use clap::{Args, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
-use tokio::io::{AsyncReadExt, duplex};
-use tonic::{Request, async_trait};
-use tracing::{debug, error, info};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, duplex};
+use tokio::sync::{mpsc, oneshot};
+use tonic::transport::Uri;
+use tonic::{Code, Request, Status, async_trait};
+use tracing::{debug, error, info, warn};
 
+use crate::command::controlplane::{self, ControlPlaneClient};
+use crate::command::prompt;
+use crate::command::push::archive::{ArchiveConfig, ManifestEntry};
+use crate::command::push::docker::DockerBuildConfig;
+use crate::command::push::manifest::{ArtifactManifest, add_manifest};
+use crate::command::push::native::NativeBuildConfig;
+use crate::command::push::node::NodeBuildConfig;
+use crate::command::push::prebuilt::PrebuiltConfig;
+use crate::command::push::python::PythonBuildConfig;
 use crate::command::push::rust::RustBuildConfig;
+use crate::command::push::zig::ZigBuildConfig;
+use crate::command::transport::{
+    self, AuthChannel, AuthToken, ConnectOptions, GrpcCompression, MessageLimits, RetryPolicy,
+    TimeoutConfig, TlsConfig,
+};
 
+mod archive;
 mod custom;
+mod docker;
+mod env;
+mod inputs;
+mod manifest;
+mod native;
+mod node;
+mod prebuilt;
+mod python;
 mod rust;
+mod zig;
+
+pub(super) use env::expand_vars;
+
+pub(super) const CONFIG_FILE: &str = "Nocti.toml";
+
+/// Algorithm prefix of artifact digests, e.g. `sha256:<hex>`
+pub(super) const DIGEST_PREFIX: &str = "sha256:";
+
+/// Default size of the in-memory tar pipe and of each uploaded chunk
+const DEFAULT_STREAM_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Default number of upload frames read ahead of the network
+const DEFAULT_PUSH_BUFFER_FRAMES: usize = 4;
+const DEFAULT_SET_NAME_RETRIES: u32 = 3;
 
-const CONFIG_FILE: &str = "Nocti.toml";
+/// YAML alternatives to `Nocti.toml`
+const YAML_CONFIG_FILES: [&str; 2] = ["Nocti.yaml", "Nocti.yml"];
 
 #[async_trait]
-trait BuildService {
+trait BuildService: Send + Sync {
     async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()>;
+
+    /// Run the build and report where its output is. Builds producing a single file can
+    /// return it in place instead of copying it into `temp_path`
+    async fn build_output(
+        &self,
+        project_path: PathBuf,
+        temp_path: PathBuf,
+    ) -> anyhow::Result<BuildOutput> {
+        self.build(project_path, temp_path.clone()).await?;
+        Ok(BuildOutput::Directory(temp_path))
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Project {
-    name: String,
+/// Files a build produced for the archive
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum BuildOutput {
+    /// Every file below the build's temporary directory
+    Directory(PathBuf),
+    /// A single file archived from where the build left it, with `name` as its entry name
+    File { path: PathBuf, name: String },
+}
+
+/// A finished build, keeping its temporary directory until the artifact is archived
+pub(super) struct BuildArtifact {
+    temp_dir: TempDir,
+    pub(super) output: BuildOutput,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Project {
+    /// Key the artifact is registered under, inferred from Cargo.toml for Rust builds
+    #[serde(default)]
+    pub(super) name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct Config {
-    project: Project,
-    build: Build,
+pub(super) struct Config {
+    #[serde(default)]
+    pub(super) project: Project,
+    pub(super) build: Build,
+    /// Precedence: `--registry-url` > config file > `NOCTI_REGISTRY_URL` > default
     #[serde(default = "default_registry_url")]
-    registry_url: String,
+    pub(super) registry_url: String,
+    /// Precedence: `--control-plane-url` > config file > `NOCTI_CONTROL_PLANE_URL` > default
     #[serde(default = "default_control_plane_url")]
-    control_plane_url: String,
+    pub(super) control_plane_url: String,
+    #[serde(default)]
+    tls: TlsConfig,
+    #[serde(default)]
+    auth_token: Option<AuthToken>,
+    #[serde(default)]
+    timeouts: TimeoutConfig,
+    /// gRPC message compression (defaults to $NOCTI_GRPC_COMPRESSION or none)
+    #[serde(default)]
+    grpc_compression: Option<GrpcCompression>,
+    /// Ask for confirmation before pushing to a registry that is not on this machine
+    #[serde(default)]
+    confirm_remote: bool,
+    /// Times to retry the upload with a fresh archive after a transient failure (default: 0)
+    #[serde(default)]
+    push_retries: u32,
+    /// Abort the push once the archive grows past this many bytes (default: unlimited)
+    #[serde(default)]
+    max_artifact_bytes: Option<u64>,
+    /// Upload frames buffered between the tar reader and the network (default: 4).
+    /// More frames keep the network busy while archiving stalls, at the cost of up to
+    /// this many stream buffers of memory; fewer frames apply backpressure sooner
+    #[serde(default = "default_push_buffer_frames")]
+    push_buffer_frames: usize,
+    /// Times to retry associating the pushed digest with the key after a transient
+    /// failure (default: 3). The association is idempotent for the same key and digest
+    #[serde(default = "default_set_name_retries")]
+    set_name_retries: u32,
+    /// Add a `manifest.json` with the project name, build type, git commit, build time and
+    /// CLI version to the pushed artifact (default: false)
+    #[serde(default)]
+    include_manifest: bool,
+    #[serde(flatten)]
+    pub(super) archive: ArchiveConfig,
+}
+
+impl Config {
+    /// Connection settings for the registry and control plane, falling back to the environment
+    pub(super) fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions {
+            tls: self.tls.clone().with_env_fallback(),
+            auth_token: self.auth_token.clone(),
+            timeouts: self.timeouts.clone().with_env_fallback(),
+            limits: MessageLimits::from_env(),
+            compression: self
+                .grpc_compression
+                .unwrap_or_else(GrpcCompression::from_env),
+        }
+    }
+}
+
+fn default_push_buffer_frames() -> usize {
+    DEFAULT_PUSH_BUFFER_FRAMES
+}
+
+fn default_set_name_retries() -> u32 {
+    DEFAULT_SET_NAME_RETRIES
+}
+
+fn default_entrypoint() -> String {
+    "bootstrap".to_string()
+}
+
+/// Compress the binary in place with `upx --best`, warning instead of failing
+/// when `upx` is unavailable or cannot handle the binary
+async fn compress_binary(path: &Path) -> Result<()> {
+    let size_before = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to read metadata of {:?}", path))?
+        .len();
+
+    let status = match tokio::process::Command::new("upx")
+        .arg("--best")
+        .arg("-q")
+        .arg(path)
+        .status()
+        .await
+    {
+        Ok(status) => status,
+        Err(e) => {
+            warn!("Skipping binary compression, 'upx' could not be run: {}", e);
+            return Ok(());
+        }
+    };
+
+    if !status.success() {
+        warn!("Skipping binary compression, 'upx' failed for {:?}", path);
+        return Ok(());
+    }
+
+    let size_after = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to read metadata of {:?}", path))?
+        .len();
+
+    info!(
+        "Compressed binary from {} to {} bytes ({} bytes saved)",
+        size_before,
+        size_after,
+        size_before.saturating_sub(size_after)
+    );
+
+    Ok(())
+}
+
+/// Run a build step with inherited output, failing when it takes longer than
+/// `timeout_seconds` or exits non-zero
+async fn run_with_timeout(
+    mut cmd: tokio::process::Command,
+    name: &str,
+    timeout_seconds: u64,
+) -> Result<()> {
+    cmd.stdout(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::inherit())
+        .kill_on_drop(true);
+
+    debug!("Running {} with a {}s timeout", name, timeout_seconds);
+    let status = tokio::time::timeout(Duration::from_secs(timeout_seconds), cmd.status())
+        .await
+        .with_context(|| {
+            format!(
+                "{} timed out after {} seconds. Consider increasing timeout_seconds.",
+                name, timeout_seconds
+            )
+        })?
+        .with_context(|| format!("Failed to execute {}", name))?;
+
+    if !status.success() {
+        bail!(
+            "{} failed with exit code: {}",
+            name,
+            status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        );
+    }
+
+    Ok(())
+}
+
+/// Shell used for build commands unless one is configured, `cmd` on Windows and `sh` elsewhere
+fn default_shell() -> String {
+    if cfg!(target_os = "windows") {
+        "cmd".to_string()
+    } else {
+        "sh".to_string()
+    }
+}
+
+/// Command running `command` through the platform's default shell
+fn shell_command(command: &str) -> tokio::process::Command {
+    shell_command_with(&default_shell(), command)
+}
+
+/// Command running `command` through `shell`
+fn shell_command_with(shell: &str, command: &str) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new(shell);
+    cmd.args(shell_args(shell)).arg(command);
+    cmd
+}
+
+/// Arguments that make `shell` run a single command, "powershell" and "pwsh" taking
+/// `-Command`
+fn shell_args(shell: &str) -> Vec<&'static str> {
+    // Accept full paths such as `C:\...\pwsh.exe` by looking at the file stem only
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(shell)
+        .to_ascii_lowercase();
+
+    match name.as_str() {
+        "cmd" => vec!["/C"],
+        "powershell" | "pwsh" => vec!["-NoProfile", "-NonInteractive", "-Command"],
+        _ => vec!["-c"],
+    }
 }
 
-fn default_registry_url() -> String {
+/// Verify `program` runs successfully with `args`, e.g. `--version`, before building
+async fn require_tool(program: &str, args: &[&str]) -> Result<()> {
+    let check = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await;
+
+    match check {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => bail!(
+            "'{} {}' failed. Please ensure {} is installed and in PATH",
+            program,
+            args.join(" "),
+            program
+        ),
+    }
+}
+
+/// Mark the file as executable (0o755) so the worker can run it; a no-op on Windows
+async fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .await
+            .with_context(|| format!("Failed to make {:?} executable", path))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+pub(super) fn default_registry_url() -> String {
     std::env::var("NOCTI_REGISTRY_URL").unwrap_or_else(|_| "http://localhost:50001".to_string())
 }
 
-fn default_control_plane_url() -> String {
+pub(super) fn default_control_plane_url() -> String {
     std::env::var("NOCTI_CONTROL_PLANE_URL")
         .unwrap_or_else(|_| "http://localhost:50002".to_string())
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type")]
-enum Build {
+pub(super) enum Build {
     #[serde(rename = "custom")]
     Custom(CustomBuild),
     #[serde(rename = "rust")]
     Rust(RustBuildConfig),
+    #[serde(rename = "prebuilt")]
+    Prebuilt(PrebuiltConfig),
+    #[serde(rename = "docker")]
+    Docker(DockerBuildConfig),
+    #[serde(rename = "node")]
+    Node(NodeBuildConfig),
+    #[serde(rename = "zig")]
+    Zig(ZigBuildConfig),
+    #[serde(rename = "native")]
+    Native(NativeBuildConfig),
+    #[serde(rename = "python")]
+    Python(PythonBuildConfig),
 }
 
-pub async fn run(path: &str) -> Result<()> {
-    let project_path = Path::new(path);
-    info!("Running push command on path: {:?}", project_path);
+impl Build {
+    /// The build's `type` in the config
+    fn type_name(&self) -> &'static str {
+        match self {
+            Build::Custom(_) => "custom",
+            Build::Rust(_) => "rust",
+            Build::Prebuilt(_) => "prebuilt",
+            Build::Docker(_) => "docker",
+            Build::Node(_) => "node",
+            Build::Zig(_) => "zig",
+            Build::Native(_) => "native",
+            Build::Python(_) => "python",
+        }
+    }
+}
+
+/// Whether the project has a `Nocti.toml`, `Nocti.yaml` or `Nocti.yml`
+pub(super) fn has_config_file(project_path: &Path) -> bool {
+    !existing_config_files(project_path).is_empty()
+}
+
+/// The project's `Nocti.toml`, `Nocti.yaml` and `Nocti.yml` files that exist
+pub(super) fn existing_config_files(project_path: &Path) -> Vec<PathBuf> {
+    std::iter::once(CONFIG_FILE)
+        .chain(YAML_CONFIG_FILES)
+        .map(|name| project_path.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Find the project's config file, erroring if more than one of `Nocti.toml`,
+/// `Nocti.yaml` and `Nocti.yml` exists
+fn find_config_file(project_path: &Path) -> Result<PathBuf> {
+    let existing = existing_config_files(project_path);
+
+    match existing.as_slice() {
+        [] => Ok(project_path.join(CONFIG_FILE)),
+        [path] => Ok(path.clone()),
+        _ => bail!(
+            "Found multiple config files in {:?}: {:?}. Keep only one",
+            project_path,
+            existing
+        ),
+    }
+}
+
+/// Parse config content as YAML or TOML depending on the file extension
+fn parse_config(content: &str, path: &Path) -> Result<Config> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => {
+            serde_yaml::from_str(content).context("Failed to parse config file as YAML")
+        }
+        _ => toml::from_str(content).context("Failed to parse config file as TOML"),
+    }
+}
 
+/// Validate the project directory and load its config file, from `config_path`
+/// when given and `Nocti.toml` (or `Nocti.yaml`) in the project otherwise
+pub(super) fn load_config(project_path: &Path, config_path: Option<&Path>) -> Result<Config> {
     // Validate project path
     if !project_path.is_dir() {
         error!("Provided path is invalid: {:?}", project_path);
@@ -71,10 +423,13 @@ pub async fn run(path: &str) -> Result<()> {
     }
 
     // Validate config file exists
-    let config_file_path = project_path.join(CONFIG_FILE);
+    let config_file_path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => find_config_file(project_path)?,
+    };
     if !config_file_path.is_file() {
         error!("Missing config file at: {:?}", config_file_path);
-        bail!("'{}' does not exist or is not a file", CONFIG_FILE);
+        bail!("{:?} does not exist or is not a file", config_file_path);
     }
 
     // Load and parse config
@@ -82,141 +437,1322 @@ pub async fn run(path: &str) -> Result<()> {
     let config_content = std::fs::read_to_string(&config_file_path)
         .with_context(|| format!("Failed to read config file: {:?}", config_file_path))?;
 
-    let config: Config =
-        toml::from_str(&config_content).context("Failed to parse config file as TOML")?;
+    let config_content =
+        env::expand_env(&config_content).context("Failed to expand environment variables")?;
+
+    let config = parse_config(&config_content, &config_file_path)?;
 
     debug!("Parsed config: {:?}", config);
 
-    // Create build service
-    let buildservice: Box<dyn BuildService + Send + Sync> = match config.build {
+    Ok(config)
+}
+
+/// Fetch what resolving and building a Rust project share up front, so name inference,
+/// validation and the build run `cargo metadata` once
+pub(super) async fn load_metadata(project_path: &Path, build: &mut Build) -> Result<()> {
+    if let Build::Rust(rb_config) = build {
+        rb_config
+            .load_metadata(project_path)
+            .await
+            .context("Failed to read the Rust project")?;
+    }
+
+    Ok(())
+}
+
+/// The configured project name, or for Rust builds the name of the resolved package
+pub(super) async fn project_name(project_path: &Path, config: &Config) -> Result<String> {
+    if let Some(ref name) = config.project.name {
+        return Ok(name.clone());
+    }
+
+    match config.build {
+        Build::Rust(ref rb_config) => {
+            let resolved = rb_config
+                .clone()
+                .resolve(project_path)
+                .await
+                .context("Failed to infer the project name, set [project] name")?;
+            debug!("Inferred project name '{}'", resolved.package);
+            Ok(resolved.package)
+        }
+        Build::Custom(_)
+        | Build::Prebuilt(_)
+        | Build::Docker(_)
+        | Build::Node(_)
+        | Build::Zig(_)
+        | Build::Native(_)
+        | Build::Python(_) => {
+            bail!("[project] name is required for all but Rust builds")
+        }
+    }
+}
+
+/// Create the build service for the configured build type
+fn create_build_service(build: Build) -> Result<Box<dyn BuildService + Send + Sync>> {
+    let buildservice: Box<dyn BuildService + Send + Sync> = match build {
         Build::Custom(cb) => {
             debug!("Using custom build");
             Box::new(cb)
         }
         Build::Rust(rb_config) => {
             debug!("Using Rust build with config: {:?}", rb_config);
-            Box::new(RustBuild::from(rb_config))
+            rb_config
+                .into_build_service()
+                .context("Invalid Rust build configuration")?
+        }
+        Build::Prebuilt(prebuilt) => {
+            debug!("Using prebuilt artifact: {:?}", prebuilt);
+            Box::new(prebuilt)
+        }
+        Build::Docker(docker) => {
+            debug!("Using docker build with config: {:?}", docker);
+            Box::new(docker)
+        }
+        Build::Node(node) => {
+            debug!("Using node build with config: {:?}", node);
+            Box::new(node)
+        }
+        Build::Zig(zig) => {
+            debug!("Using zig build with config: {:?}", zig);
+            Box::new(zig)
+        }
+        Build::Native(native) => {
+            debug!("Using native build with config: {:?}", native);
+            Box::new(native)
+        }
+        Build::Python(python) => {
+            debug!("Using python build with config: {:?}", python);
+            Box::new(python)
         }
     };
 
+    Ok(buildservice)
+}
+
+/// Check the build configuration without running the build, describing what it resolves to
+pub(super) async fn validate_build(project_path: &Path, build: &Build) -> Result<String> {
+    match build {
+        Build::Custom(cb) => {
+            cb.validate()
+                .context("Invalid custom build configuration")?;
+            Ok("custom build".to_string())
+        }
+        Build::Rust(rb_config) => {
+            let resolved = rb_config
+                .clone()
+                .resolve(project_path)
+                .await
+                .context("Invalid Rust build configuration")?;
+            Ok(format!(
+                "package '{}', binary '{}'",
+                resolved.package, resolved.binary
+            ))
+        }
+        Build::Prebuilt(prebuilt) => {
+            let source = prebuilt.source_path(project_path)?;
+            Ok(format!("prebuilt artifact {:?}", source))
+        }
+        Build::Docker(docker) => {
+            let dockerfile = docker
+                .validate(project_path)
+                .context("Invalid docker build configuration")?;
+            Ok(format!("docker build of {:?}", dockerfile))
+        }
+        Build::Node(node) => {
+            node.validate(project_path)
+                .context("Invalid node build configuration")?;
+            Ok("node build".to_string())
+        }
+        Build::Zig(zig) => {
+            zig.validate(project_path)
+                .context("Invalid zig build configuration")?;
+            Ok("zig build".to_string())
+        }
+        Build::Native(native) => {
+            native
+                .validate(project_path)
+                .context("Invalid native build configuration")?;
+            Ok("native build".to_string())
+        }
+        Build::Python(python) => {
+            python
+                .validate(project_path)
+                .context("Invalid python build configuration")?;
+            Ok("python build".to_string())
+        }
+    }
+}
+
+/// Run the configured build, returning its output and the temporary directory backing it.
+/// With `keep_temp` the directory is left on disk, even when the build fails.
+pub(super) async fn build_artifact(
+    project_path: &Path,
+    build: Build,
+    keep_temp: bool,
+) -> Result<BuildArtifact> {
+    // Create build service
+    let buildservice = create_build_service(build)?;
+
     // Create temporary directory for build output
     debug!("Creating temporary directory for build artifacts");
     let temp_dir = tempfile::Builder::new()
         .prefix("nocti-build-")
+        .disable_cleanup(keep_temp)
         .tempdir()
         .context("Failed to create temporary directory")?;
 
     let temp_path = temp_dir.path().to_path_buf();
     debug!("Temporary directory created at: {:?}", temp_path);
+    if keep_temp {
+        info!(path = %temp_path.display(), "Keeping build directory");
+    }
 
     // Run the build
     info!("Starting build...");
-    buildservice
-        .build(project_path.to_path_buf(), temp_path.clone())
+    let output = buildservice
+        .build_output(project_path.to_path_buf(), temp_path)
         .await
         .context("Build failed")?;
     info!("Build completed successfully");
 
-    // Create tar archive and stream it
-    let (writer, mut reader) = duplex(8 * 1024);
-    info!("Creating in-memory tar archive...");
-
-    let tar_task = tokio::spawn(async move {
-        let temp_path = temp_dir.path();
-
-        let mut builder = tokio_tar::Builder::new(writer);
-        if let Err(e) = builder.append_dir_all(".", temp_path).await {
-            error!("Failed to add directory to tar: {}", e);
-            return Err(anyhow::anyhow!("tar append_dir_all error: {}", e));
-        }
-        if let Err(e) = builder.finish().await {
-            error!("Failed to finalize tar archive: {}", e);
-            return Err(anyhow::anyhow!("tar finish error: {}", e));
-        }
-        debug!("Tarball creation completed successfully");
-        Ok(())
-    });
-
-    // Create a stream of RegistryPushRequest from reader
-    let outbound = async_stream::stream! {
-        let mut buf = [0u8; 8192];
-        loop {
-            match reader.read(&mut buf).await {
-                Ok(0) => {
-                    debug!("Finished reading all tar data");
-                    break;
-                }
-                Ok(n) => {
-                    debug!("Read {} bytes from tar stream", n);
-                    let req = RegistryPushRequest {
-                        data: buf[..n].to_vec(),
-                    };
-                    yield req;
+    Ok(BuildArtifact { temp_dir, output })
+}
+
+/// Run the build and add the provenance manifest to its output, if one is given
+async fn build_with_manifest(
+    project_path: &Path,
+    build: Build,
+    keep_temp: bool,
+    manifest: Option<&ArtifactManifest>,
+) -> Result<BuildArtifact> {
+    let mut artifact = build_artifact(project_path, build, keep_temp).await?;
+    if let Some(manifest) = manifest {
+        add_manifest(&mut artifact, manifest).await?;
+    }
+
+    Ok(artifact)
+}
+
+/// Write the build output as a tar archive. A directory honors `.noctiignore`, a single
+/// file is archived as is.
+pub(super) async fn write_archive<W>(
+    writer: W,
+    project_path: &Path,
+    output: &BuildOutput,
+    config: &ArchiveConfig,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    match output {
+        BuildOutput::Directory(root) => {
+            let ignore = archive::load_ignore(project_path)?;
+            archive::write_archive(writer, root, &ignore, config).await
+        }
+        BuildOutput::File { path, name } => {
+            archive::write_file_archive(writer, path, name, config).await
+        }
+    }
+}
+
+/// List the files the archive of `output` will contain
+fn output_manifest(
+    project_path: &Path,
+    output: &BuildOutput,
+    config: &ArchiveConfig,
+) -> Result<Vec<ManifestEntry>> {
+    let mut files = match output {
+        BuildOutput::Directory(root) => {
+            let ignore = archive::load_ignore(project_path)?;
+            archive::manifest(root, &ignore)?
+        }
+        BuildOutput::File { path, name } => {
+            let metadata = std::fs::metadata(path)
+                .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+            vec![ManifestEntry {
+                path: PathBuf::from(name),
+                size: metadata.len(),
+            }]
+        }
+    };
+
+    if let Some(package_root) = config.package_root()? {
+        for file in &mut files {
+            file.path = package_root.join(&file.path);
+        }
+    }
+
+    Ok(files)
+}
+
+#[derive(Args, Debug)]
+pub struct PushArgs {
+    path: String,
+    /// Config file to use instead of <PATH>/Nocti.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Build and show what would be pushed without contacting any service
+    #[arg(long)]
+    dry_run: bool,
+    /// Read the mapping back from the control plane and check it matches
+    #[arg(long)]
+    verify: bool,
+    /// Registry URL, taking precedence over Nocti.toml, then $NOCTI_REGISTRY_URL,
+    /// then http://localhost:50001
+    #[arg(long)]
+    registry_url: Option<String>,
+    /// Control plane URL, taking precedence over Nocti.toml, then
+    /// $NOCTI_CONTROL_PLANE_URL, then http://localhost:50002
+    #[arg(long)]
+    control_plane_url: Option<String>,
+    /// Format of the key and digest printed on success
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Fail instead of warning when pushing a debug build to a non-local registry
+    #[arg(long)]
+    no_debug_to_remote: bool,
+    /// Ask for confirmation before pushing to a non-local registry
+    #[arg(long)]
+    confirm: bool,
+    /// Skip the remote registry confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Keep the build directory instead of deleting it, for debugging
+    #[arg(long)]
+    keep_temp: bool,
+    /// Package to build, overriding `package_name` for Rust builds
+    #[arg(long)]
+    package: Option<String>,
+    /// Binary to build, overriding `binary_name` for Rust builds
+    #[arg(long)]
+    binary: Option<String>,
+    /// Target triple, overriding `target` for Rust builds
+    #[arg(long)]
+    target: Option<String>,
+    /// Warn instead of failing when a Rust build's binary is not at the expected path,
+    /// packaging the executable cargo reported instead
+    #[arg(long)]
+    no_verify_build: bool,
+    /// Label recorded with the mapping, e.g. a version (defaults to the short git commit hash)
+    #[arg(long)]
+    tag: Option<String>,
+    /// Skip the build and push when the project inputs match the last push, which is
+    /// recorded with an inputs-<hash> tag instead of the git commit
+    #[arg(long, conflicts_with = "tag")]
+    if_changed: bool,
+    /// Abort the whole push, build included, after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    deadline: Option<u64>,
+}
+
+/// Step of a push, reported when `--deadline` expires
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Preparing,
+    Comparing,
+    Building,
+    Connecting,
+    Uploading,
+    Associating,
+    Verifying,
+}
+
+impl Phase {
+    fn describe(self) -> &'static str {
+        match self {
+            Phase::Preparing => "preparing the push",
+            Phase::Comparing => "comparing the inputs with the last push",
+            Phase::Building => "building the artifact",
+            Phase::Connecting => "connecting to the registry and control plane",
+            Phase::Uploading => "uploading the artifact",
+            Phase::Associating => "associating the digest with the key",
+            Phase::Verifying => "verifying the stored digest",
+        }
+    }
+}
+
+/// The phase a push is in, readable after the push was cancelled
+#[derive(Debug)]
+struct PhaseTracker(Mutex<Phase>);
+
+impl PhaseTracker {
+    fn new() -> Self {
+        Self(Mutex::new(Phase::Preparing))
+    }
+
+    fn set(&self, phase: Phase) {
+        debug!("Push phase: {}", phase.describe());
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = phase;
+    }
+
+    fn get(&self) -> Phase {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Run `push`, cancelling it once `deadline` passes. Dropping the push kills build
+/// processes and closes the upload stream, which stops the archive tasks feeding it.
+async fn with_deadline<F>(deadline: Option<Duration>, phase: &PhaseTracker, push: F) -> Result<()>
+where
+    F: std::future::Future<Output = Result<()>>,
+{
+    let Some(deadline) = deadline else {
+        return push.await;
+    };
+
+    match tokio::time::timeout(deadline, push).await {
+        Ok(result) => result,
+        Err(_) => bail!(
+            "Push did not finish within the {} second deadline, it was {}. \
+            Consider increasing --deadline.",
+            deadline.as_secs(),
+            phase.get().describe()
+        ),
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Render the pushed key and digest for stdout
+fn format_result(format: OutputFormat, key: &str, digest: &str) -> String {
+    match format {
+        OutputFormat::Text => format!("{}\t{}", key, digest),
+        OutputFormat::Json => serde_json::json!({ "key": key, "digest": digest }).to_string(),
+    }
+}
+
+/// Render a key whose inputs did not change since it was pushed as `digest`
+fn format_up_to_date(format: OutputFormat, key: &str, digest: &str) -> String {
+    match format {
+        OutputFormat::Text => format!("{} is up to date ({}), nothing to push", key, digest),
+        OutputFormat::Json => {
+            serde_json::json!({ "key": key, "digest": digest, "up_to_date": true }).to_string()
+        }
+    }
+}
+
+/// Stream buffer size from `NOCTI_STREAM_BUFFER`, in bytes
+fn stream_buffer_size() -> usize {
+    parse_stream_buffer(std::env::var("NOCTI_STREAM_BUFFER").ok().as_deref())
+}
+
+/// Parse a stream buffer size, falling back to the default when unset or invalid
+fn parse_stream_buffer(value: Option<&str>) -> usize {
+    match value.map(str::parse::<usize>) {
+        Some(Ok(size)) if size > 0 => size,
+        Some(_) => {
+            warn!(
+                "Ignoring invalid NOCTI_STREAM_BUFFER, using {} bytes",
+                DEFAULT_STREAM_BUFFER_BYTES
+            );
+            DEFAULT_STREAM_BUFFER_BYTES
+        }
+        None => DEFAULT_STREAM_BUFFER_BYTES,
+    }
+}
+
+/// Whether `total` bytes is over the configured artifact size limit
+fn exceeds_limit(total: u64, max_artifact_bytes: Option<u64>) -> bool {
+    max_artifact_bytes.is_some_and(|max| total > max)
+}
+
+/// Whether `url` points at this machine
+fn is_local_url(url: &str) -> bool {
+    let Some(host) = url
+        .parse::<Uri>()
+        .ok()
+        .and_then(|uri| uri.host().map(str::to_string))
+    else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    host.eq_ignore_ascii_case("localhost")
+        || host.to_ascii_lowercase().ends_with(".localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Warn about, or with `strict` refuse, pushing a debug build to a remote registry
+fn check_debug_target(build: &Build, registry_url: &str, strict: bool) -> Result<()> {
+    let Build::Rust(rb_config) = build else {
+        return Ok(());
+    };
+    if !rb_config.is_debug() || is_local_url(registry_url) {
+        return Ok(());
+    }
+
+    if strict {
+        bail!(
+            "Refusing to push a debug build to remote registry {} (--no-debug-to-remote)",
+            registry_url
+        );
+    }
+    warn!(
+        "Pushing a DEBUG build to remote registry {}. \
+        Set profile = \"release\" unless this is intended",
+        registry_url
+    );
+    Ok(())
+}
+
+/// Have the user type the project name before pushing to `registry_url`,
+/// skipping the prompt when stdin is not a terminal
+fn confirm_remote_push(key: &str, registry_url: &str) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        warn!("Not a terminal, skipping the remote registry confirmation");
+        return Ok(());
+    }
+
+    let question = format!("About to push '{}' to {}.", key, registry_url);
+    if !prompt::confirm_typed(&question, key)? {
+        bail!("Push aborted, the project name did not match");
+    }
+    Ok(())
+}
+
+/// Spinner counting uploaded bytes, hidden when quiet or stdout is not a terminal
+fn upload_progress(quiet: bool) -> ProgressBar {
+    if quiet || !std::io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let progress = ProgressBar::new_spinner();
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} Uploading {bytes} ({binary_bytes_per_sec})")
+            .expect("valid progress template"),
+    );
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
+
+/// One attempt at streaming the build output to the registry
+struct Upload<'a> {
+    project_path: &'a Path,
+    output: &'a BuildOutput,
+    archive: &'a ArchiveConfig,
+    max_artifact_bytes: Option<u64>,
+    push_timeout: Option<Duration>,
+    buffer_size: usize,
+    buffer_frames: usize,
+    quiet: bool,
+}
+
+/// Read `buffer_size` chunks from the tar stream into upload frames until it ends.
+/// Once more than `max_artifact_bytes` are read the total is sent on `limit_tx` and
+/// reading stops without closing `frames`, so the registry never sees a truncated artifact.
+async fn read_frames<R>(
+    mut reader: R,
+    buffer_size: usize,
+    max_artifact_bytes: Option<u64>,
+    frames: mpsc::Sender<RegistryPushRequest>,
+    limit_tx: oneshot::Sender<u64>,
+) where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = vec![0u8; buffer_size];
+    let mut total: u64 = 0;
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) => {
+                debug!("Finished reading all tar data");
+                break;
+            }
+            Ok(n) => {
+                debug!("Read {} bytes from tar stream", n);
+                total += n as u64;
+                if exceeds_limit(total, max_artifact_bytes) {
+                    let _ = limit_tx.send(total);
+                    std::future::pending::<()>().await;
                 }
-                Err(e) => {
-                    error!("Error reading from tar stream: {}", e);
+                let req = RegistryPushRequest {
+                    data: buf[..n].to_vec(),
+                };
+                if frames.send(req).await.is_err() {
+                    debug!("Upload stream closed, stopping tar reader");
                     break;
                 }
             }
+            Err(e) => {
+                error!("Error reading from tar stream: {}", e);
+                break;
+            }
         }
+    }
+}
+
+impl Upload<'_> {
+    /// Archive the build output into a fresh stream and push it, returning the digest.
+    /// A failed call is returned as the bare [`Status`] so the caller can decide to retry.
+    async fn push(&self, client: &mut RegistryServiceClient<AuthChannel>) -> Result<String> {
+        // Create tar archive and stream it
+        let (writer, reader) = duplex(self.buffer_size);
+        info!("Creating in-memory tar archive...");
+
+        let archive_project_path = self.project_path.to_path_buf();
+        let output = self.output.clone();
+        let archive_config = self.archive.clone();
+        let tar_task = tokio::spawn(async move {
+            if let Err(e) =
+                write_archive(writer, &archive_project_path, &output, &archive_config).await
+            {
+                error!("Failed to create tar archive: {:#}", e);
+                return Err(e);
+            }
+            debug!("Tarball creation completed successfully");
+            Ok(())
+        });
+
+        // Read frames ahead of the network, bounded by the frame buffer
+        let max_artifact_bytes = self.max_artifact_bytes;
+        let (limit_tx, mut limit_rx) = oneshot::channel::<u64>();
+        let (frame_tx, mut frame_rx) = mpsc::channel(self.buffer_frames);
+        let reader_task = tokio::spawn(read_frames(
+            reader,
+            self.buffer_size,
+            max_artifact_bytes,
+            frame_tx,
+            limit_tx,
+        ));
+
+        // Create a stream of RegistryPushRequest from the buffered frames, hashing
+        // exactly the bytes that are sent
+        let progress = upload_progress(self.quiet);
+        let upload_progress = progress.clone();
+        let (digest_tx, mut digest_rx) = oneshot::channel::<String>();
+        let outbound = async_stream::stream! {
+            let mut hasher = Sha256::new();
+            while let Some(req) = frame_rx.recv().await {
+                upload_progress.inc(req.data.len() as u64);
+                hasher.update(&req.data);
+                yield req;
+            }
+            let _ = digest_tx.send(format!("{}{:x}", DIGEST_PREFIX, hasher.finalize()));
+        };
+
+        info!("Sending tar data to registry...");
+        let mut request = Request::new(outbound);
+        if let Some(timeout) = self.push_timeout {
+            request.set_timeout(timeout);
+        }
+        let response = tokio::select! {
+            response = client.push(request) => response,
+            Ok(total) = &mut limit_rx => {
+                progress.finish_and_clear();
+                reader_task.abort();
+                tar_task.abort();
+                bail!(
+                    "Artifact exceeds max_artifact_bytes ({} bytes), aborted after {} bytes",
+                    max_artifact_bytes.unwrap_or_default(),
+                    total
+                );
+            }
+        };
+        progress.finish_and_clear();
+
+        let response = match response {
+            Ok(response) => response.into_inner(),
+            Err(status) => {
+                reader_task.abort();
+                tar_task.abort();
+                return Err(status.into());
+            }
+        };
+        info!(artifact_bytes = progress.position(), "Upload finished");
+
+        match digest_rx.try_recv() {
+            Ok(client_digest) => check_digest(&client_digest, &response.digest),
+            Err(_) => warn!("Registry responded before the whole artifact was sent"),
+        }
+
+        // Wait for tar task to complete
+        tar_task.await.context("Tar creation task panicked")??;
+
+        Ok(response.digest)
+    }
+}
+
+pub async fn run(args: PushArgs, quiet: bool, verbose: bool) -> Result<()> {
+    let deadline = match args.deadline {
+        Some(0) => bail!("--deadline must be greater than 0"),
+        deadline => deadline.map(Duration::from_secs),
     };
 
-    // Connect to registry and push
-    info!(
-        "Connecting to RegistryService at {}...",
-        config.registry_url
-    );
-    let mut registry_client = RegistryServiceClient::connect(config.registry_url.clone())
-        .await
-        .with_context(|| {
-            format!(
-                "Failed to connect to RegistryService at {}",
-                config.registry_url
-            )
-        })?;
+    let phase = PhaseTracker::new();
+    with_deadline(deadline, &phase, push(args, quiet, verbose, &phase)).await
+}
 
-    info!("Sending tar data to registry...");
-    let response = registry_client
-        .push(Request::new(outbound))
-        .await
-        .context("Failed to push to registry")?
-        .into_inner();
+/// Build the project and push it, recording the active step in `phase`
+async fn push(args: PushArgs, quiet: bool, verbose: bool, phase: &PhaseTracker) -> Result<()> {
+    let PushArgs {
+        path,
+        config: config_path,
+        dry_run,
+        verify,
+        registry_url,
+        control_plane_url,
+        output,
+        no_debug_to_remote,
+        confirm,
+        yes,
+        keep_temp,
+        package,
+        binary,
+        target,
+        no_verify_build,
+        tag,
+        if_changed,
+        deadline: _,
+    } = args;
+
+    let project_path = Path::new(&path);
+    info!(path = ?project_path, "Running push command");
+
+    let mut config = load_config(project_path, config_path.as_deref())?;
+
+    // Command line choices change the build without changing any input file
+    let selection = [&package, &binary, &target].map(|value| format!("{:?}", value));
+
+    // Rust build selection from the command line wins over the config file
+    if package.is_some() || binary.is_some() || target.is_some() {
+        let Build::Rust(ref mut rb_config) = config.build else {
+            bail!("--package, --binary and --target can only be used with Rust builds");
+        };
+        debug!("Overriding Rust build selection from the command line");
+        rb_config.override_with(package, binary, target);
+    }
+
+    if no_verify_build {
+        let Build::Rust(ref mut rb_config) = config.build else {
+            bail!("--no-verify-build can only be used with Rust builds");
+        };
+        rb_config.skip_verify_build();
+    }
+
+    // On a terminal cargo's output collapses to a progress line, unless logging is verbose
+    if let Build::Rust(ref mut rb_config) = config.build {
+        rb_config.show_progress(!quiet && !verbose && std::io::stderr().is_terminal());
+    }
 
-    debug!("Registry responded with digest: {}", response.digest);
+    load_metadata(project_path, &mut config.build).await?;
+    let key = project_name(project_path, &config).await?;
 
-    // Wait for tar task to complete
-    tar_task.await.context("Tar creation task panicked")??;
+    let input_tag = if if_changed {
+        let input_tag = inputs::input_tag(
+            project_path,
+            &config.build,
+            config_path.as_deref(),
+            &selection,
+        )
+        .context("Failed to hash the project inputs")?;
+        info!(tag = %input_tag, "Hashed project inputs");
+        Some(input_tag)
+    } else {
+        None
+    };
+
+    let tag = match tag {
+        Some(tag) if tag.trim().is_empty() => bail!("Tag cannot be empty"),
+        Some(tag) => Some(tag),
+        None if input_tag.is_some() => input_tag.clone(),
+        None => git_commit(project_path, true).await,
+    };
+    debug!(tag = ?tag, "Resolved push tag");
+
+    let manifest = if config.include_manifest {
+        let commit = git_commit(project_path, false).await;
+        Some(ArtifactManifest::new(
+            key.clone(),
+            config.build.type_name(),
+            commit,
+        ))
+    } else {
+        None
+    };
+
+    // Command line flags take precedence over the config file and environment
+    if let Some(url) = registry_url {
+        debug!("Overriding registry URL from the command line");
+        config.registry_url = url;
+    }
+    if let Some(url) = control_plane_url {
+        debug!("Overriding control plane URL from the command line");
+        config.control_plane_url = url;
+    }
+    transport::validate_url(&config.registry_url, "registry")?;
+    transport::validate_url(&config.control_plane_url, "control plane")?;
+    if config.push_buffer_frames == 0 {
+        bail!("push_buffer_frames must be greater than 0");
+    }
+
+    check_debug_target(&config.build, &config.registry_url, no_debug_to_remote)?;
+
+    let confirm = (confirm || config.confirm_remote) && !yes && !dry_run;
+    if confirm && !is_local_url(&config.registry_url) {
+        confirm_remote_push(&key, &config.registry_url)?;
+    }
+
+    if dry_run {
+        println!("Project:       {}", key);
+        println!("Registry:      {}", config.registry_url);
+        println!("Control plane: {}", config.control_plane_url);
+        println!("Tag:           {}", tag.as_deref().unwrap_or("-"));
+        println!(
+            "Build:         {}",
+            validate_build(project_path, &config.build).await?
+        );
+
+        phase.set(Phase::Building);
+        let artifact =
+            build_with_manifest(project_path, config.build, keep_temp, manifest.as_ref()).await?;
+        println!("Artifact contents:");
+        for entry in output_manifest(project_path, &artifact.output, &config.archive)? {
+            println!("  {} ({} bytes)", entry.path.display(), entry.size);
+        }
+
+        info!("Dry run complete, nothing was pushed");
+        return Ok(());
+    }
+
+    let connect_options = config.connect_options();
+
+    if let Some(ref input_tag) = input_tag {
+        phase.set(Phase::Comparing);
+        let unchanged =
+            unchanged_digest(&config.control_plane_url, &connect_options, &key, input_tag).await?;
+        if let Some(digest) = unchanged {
+            info!(key = %key, digest = %digest, "Inputs unchanged, skipping build and push");
+            if !quiet || output == OutputFormat::Json {
+                println!("{}", format_up_to_date(output, &key, &digest));
+            }
+            return Ok(());
+        }
+    }
+
+    // The upload stream gets its own, longer deadline than regular requests
+    let push_timeout = connect_options.timeouts.push_timeout();
+    let registry_options = ConnectOptions {
+        timeouts: connect_options.timeouts.clone().for_push(),
+        ..connect_options.clone()
+    };
+
+    // Connect to both services while the artifact builds
+    let connecting = tokio::spawn(connect_services(
+        config.registry_url.clone(),
+        config.control_plane_url.clone(),
+        registry_options.clone(),
+        connect_options.clone(),
+    ));
+
+    phase.set(Phase::Building);
+    let built = build_with_manifest(project_path, config.build, keep_temp, manifest.as_ref());
+    let artifact = match built.await {
+        Ok(artifact) => artifact,
+        Err(e) => {
+            connecting.abort();
+            return Err(e);
+        }
+    };
+
+    phase.set(Phase::Connecting);
+    let Connections {
+        registry: registry_channel,
+        control_plane: mut control_plane_client,
+    } = connecting.await.context("Connection task panicked")??;
+
+    let limits = registry_options.limits;
+    let mut registry_client = RegistryServiceClient::new(registry_channel)
+        .max_encoding_message_size(limits.max_encoding)
+        .max_decoding_message_size(limits.max_decoding);
+    let compression = connect_options.compression;
+    if let Some(encoding) = compression.encoding() {
+        registry_client = registry_client
+            .send_compressed(encoding)
+            .accept_compressed(encoding);
+    }
+
+    // Retry the whole upload with a fresh tar stream on transient failures
+    let upload = Upload {
+        project_path,
+        output: &artifact.output,
+        archive: &config.archive,
+        max_artifact_bytes: config.max_artifact_bytes,
+        push_timeout,
+        buffer_size: stream_buffer_size(),
+        buffer_frames: config.push_buffer_frames,
+        quiet,
+    };
+    let retry_policy = RetryPolicy {
+        retries: config.push_retries,
+        base_delay: Duration::from_secs(1),
+    };
+    phase.set(Phase::Uploading);
+    let mut attempt = 0;
+    let digest = loop {
+        let error = match upload.push(&mut registry_client).await {
+            Ok(digest) => break digest,
+            Err(e) => e,
+        };
+
+        let status = match error.downcast::<Status>() {
+            Ok(status) => status,
+            Err(e) => return Err(e),
+        };
+        if attempt >= retry_policy.retries || !transport::is_retryable_status(&status) {
+            return Err(transport::call_error(
+                status,
+                "push to registry",
+                push_timeout,
+            ));
+        }
+
+        let delay = retry_policy.delay(attempt);
+        attempt += 1;
+        warn!(
+            "Push to registry failed ({}), retrying in {:?} ({}/{})",
+            status.message(),
+            delay,
+            attempt,
+            retry_policy.retries
+        );
+        tokio::time::sleep(delay).await;
+    };
+
+    debug!(digest = %digest, "Registry responded with digest");
 
     // Associate digest with project name
-    let key = config.project.name;
-    info!("Associating digest with project key: {}", key);
+    phase.set(Phase::Associating);
+    info!(key = %key, "Associating digest with project key");
 
-    let mut control_plane_client =
-        ControlPlaneServiceClient::connect(config.control_plane_url.clone())
+    let request_timeout = connect_options.timeouts.request_timeout();
+    let retry_policy = RetryPolicy {
+        retries: config.set_name_retries,
+        base_delay: Duration::from_secs(1),
+    };
+    let tag = tag.unwrap_or_default();
+    let mut attempt = 0;
+    let response = loop {
+        let status = match control_plane_client
+            .set_digest_to_name(key.clone(), digest.clone(), tag.clone())
             .await
-            .with_context(|| {
-                format!(
-                    "Failed to connect to ControlPlaneService at {}",
-                    config.control_plane_url
-                )
-            })?;
+        {
+            Ok(response) => break response,
+            Err(status) => status,
+        };
 
-    let request = SetDigestToNameRequest {
-        key: key.clone(),
-        digest: response.digest,
+        if attempt >= retry_policy.retries || !transport::is_retryable_status(&status) {
+            let error =
+                transport::call_error(status, "set digest to name mapping", request_timeout);
+            return Err(error.context(orphaned_digest_message(&key, &digest)));
+        }
+
+        // Setting the same key and digest again is idempotent, so only this call is retried
+        let delay = retry_policy.delay(attempt);
+        attempt += 1;
+        warn!(
+            "Artifact {} is already pushed, associating it with key '{}' failed ({}), \
+            retrying in {:?} ({}/{})",
+            digest,
+            key,
+            status.message(),
+            delay,
+            attempt,
+            retry_policy.retries
+        );
+        tokio::time::sleep(delay).await;
     };
 
-    let response = control_plane_client
-        .set_digest_to_name(Request::new(request))
+    if !response.success {
+        error!("Failed to associate digest with key '{}'", key);
+        bail!("{}", rejection_message(&response.message));
+    }
+
+    info!(key = %key, "Successfully set digest for key");
+
+    if verify {
+        phase.set(Phase::Verifying);
+        verify_mapping(&mut control_plane_client, &key, &digest).await?;
+    }
+
+    // JSON output is requested explicitly, so it is printed even when quiet
+    if !quiet || output == OutputFormat::Json {
+        println!("{}", format_result(output, &key, &digest));
+    }
+
+    Ok(())
+}
+
+/// Error for a rejected mapping, including the control plane's reason when it gave one
+fn rejection_message(reason: &str) -> String {
+    match reason.trim() {
+        "" => "Control plane rejected digest to name mapping".to_string(),
+        reason => format!("Control plane rejected digest to name mapping: {}", reason),
+    }
+}
+
+/// Whether the registry's digest matches the one computed while uploading, `None` when
+/// the registry uses another algorithm and the two cannot be compared
+fn digests_match(client: &str, registry: &str) -> Option<bool> {
+    if !registry.to_ascii_lowercase().starts_with(DIGEST_PREFIX) {
+        return None;
+    }
+
+    Some(registry.eq_ignore_ascii_case(client))
+}
+
+/// Log the digest computed while uploading, warning when the registry stored different bytes
+fn check_digest(client_digest: &str, registry_digest: &str) {
+    info!(digest = %client_digest, "Computed artifact digest");
+
+    match digests_match(client_digest, registry_digest) {
+        Some(true) => debug!("Registry digest matches the uploaded bytes"),
+        Some(false) => warn!(
+            client_digest = %client_digest,
+            registry_digest = %registry_digest,
+            "Registry digest does not match the uploaded bytes, the artifact may be corrupted"
+        ),
+        None => debug!(
+            "Registry digest {} uses another algorithm, not compared",
+            registry_digest
+        ),
+    }
+}
+
+/// Error for a pushed artifact whose digest could not be associated with its key
+fn orphaned_digest_message(key: &str, digest: &str) -> String {
+    format!(
+        "Artifact {} was pushed, but associating it with key '{}' failed. \
+        Re-run the push once the control plane is reachable to set the key",
+        digest, key
+    )
+}
+
+/// Hash of the git commit checked out at `project_path`, abbreviated when `short`, if it
+/// is in a repository
+async fn git_commit(project_path: &Path, short: bool) -> Option<String> {
+    let mut cmd = tokio::process::Command::new("git");
+    cmd.arg("rev-parse");
+    if short {
+        cmd.arg("--short");
+    }
+    let output = cmd
+        .arg("HEAD")
+        .current_dir(project_path)
+        .output()
         .await
-        .context("Failed to set digest to name mapping")?
-        .into_inner();
+        .ok()?;
 
-    if response.success {
-        info!("Successfully set digest for key '{}'", key);
-        Ok(())
-    } else {
-        error!("Failed to associate digest with key '{}'", key);
-        bail!("Control plane rejected digest to name mapping")
+    if !output.status.success() {
+        debug!("No git commit found for {:?}", project_path);
+        return None;
+    }
+
+    let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// Connections to the services a push talks to
+struct Connections {
+    registry: AuthChannel,
+    control_plane: ControlPlaneClient,
+}
+
+/// Connect to the registry and control plane concurrently, logging a failure as soon
+/// as it happens so it is visible while the build is still running
+async fn connect_services(
+    registry_url: String,
+    control_plane_url: String,
+    registry_options: ConnectOptions,
+    control_plane_options: ConnectOptions,
+) -> Result<Connections> {
+    info!(registry_url = %registry_url, "Connecting to RegistryService...");
+
+    let registry = async {
+        transport::connect(&registry_url, &registry_options)
+            .await
+            .with_context(|| format!("Failed to connect to RegistryService at {}", registry_url))
+    };
+    let control_plane = controlplane::connect(&control_plane_url, &control_plane_options);
+
+    let (registry, control_plane) =
+        tokio::try_join!(registry, control_plane).inspect_err(|e| error!("{:#}", e))?;
+    debug!("Connected to RegistryService and ControlPlaneService");
+
+    Ok(Connections {
+        registry,
+        control_plane,
+    })
+}
+
+/// The digest registered for `key` when its tag shows it was pushed from the same inputs,
+/// `None` when the inputs changed or the key is not registered yet
+async fn unchanged_digest(
+    control_plane_url: &str,
+    connect_options: &ConnectOptions,
+    key: &str,
+    input_tag: &str,
+) -> Result<Option<String>> {
+    info!(key = %key, "Looking up the tag of the last push");
+    let mut client = controlplane::connect(control_plane_url, connect_options).await?;
+    let mapping = match client.get_mapping(key.to_string()).await {
+        Ok(mapping) => mapping,
+        Err(status) if status.code() == Code::NotFound => {
+            info!(key = %key, "Key is not registered yet");
+            return Ok(None);
+        }
+        Err(status) => {
+            let request_timeout = connect_options.timeouts.request_timeout();
+            return Err(transport::call_error(
+                status,
+                "look up the registered tag",
+                request_timeout,
+            ));
+        }
+    };
+
+    if !mapping.digest.is_empty() && mapping.tag.is_empty() {
+        warn!(
+            "Control plane did not report a tag for '{}', so changes cannot be detected",
+            key
+        );
+    }
+
+    Ok(inputs_unchanged(&mapping.digest, &mapping.tag, input_tag).then_some(mapping.digest))
+}
+
+/// Whether a registered digest and tag record a push of the inputs tagged `input_tag`
+fn inputs_unchanged(digest: &str, tag: &str, input_tag: &str) -> bool {
+    !digest.is_empty() && tag == input_tag
+}
+
+/// Read the mapping back from the control plane and check it points at `digest`
+async fn verify_mapping(client: &mut ControlPlaneClient, key: &str, digest: &str) -> Result<()> {
+    info!(key = %key, "Verifying stored digest");
+
+    let stored = client
+        .get_digest(key.to_string())
+        .await
+        .context("Failed to read back digest mapping")?;
+
+    if stored != digest {
+        error!("Stored digest for key '{}' does not match", key);
+        bail!(
+            "Control plane stored a different digest for '{}':\n  expected: {}\n  stored:   {}",
+            key,
+            digest,
+            stored
+        );
+    }
+
+    info!(key = %key, digest = %digest, "Verified stored digest");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust_build(profile: &str) -> Build {
+        toml::from_str(&format!("type = \"rust\"\nprofile = \"{}\"", profile)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_with_timeout() {
+        let true_cmd = tokio::process::Command::new("true");
+        assert!(run_with_timeout(true_cmd, "step", 5).await.is_ok());
+
+        let false_cmd = tokio::process::Command::new("false");
+        let err = run_with_timeout(false_cmd, "step", 5).await.unwrap_err();
+        assert_eq!(err.to_string(), "step failed with exit code: 1");
+    }
+
+    #[tokio::test]
+    async fn test_require_tool() {
+        assert!(require_tool("true", &["--version"]).await.is_ok());
+        assert!(require_tool("false", &["--version"]).await.is_err());
+
+        let err = require_tool("nocti-missing-tool", &["--version"])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("nocti-missing-tool --version"));
+    }
+
+    #[test]
+    fn test_shell_args_per_shell() {
+        let args = |shell: &str| shell_args(shell).join(" ");
+
+        assert_eq!(args("sh"), "-c");
+        assert_eq!(args("bash"), "-c");
+        assert_eq!(args("/bin/zsh"), "-c");
+        assert_eq!(args("cmd"), "/C");
+        assert_eq!(args("cmd.exe"), "/C");
+        assert_eq!(args("powershell"), "-NoProfile -NonInteractive -Command");
+        assert_eq!(args("pwsh"), "-NoProfile -NonInteractive -Command");
+        assert_eq!(
+            args("PowerShell.exe"),
+            "-NoProfile -NonInteractive -Command"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_command() {
+        let output = shell_command("echo $0-ok").output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "sh-ok");
+    }
+
+    #[test]
+    fn test_local_urls() {
+        assert!(is_local_url("http://localhost:50001"));
+        assert!(is_local_url("http://127.0.0.1:50001"));
+        assert!(is_local_url("http://[::1]:50001"));
+        assert!(is_local_url("http://registry.localhost"));
+        assert!(!is_local_url("https://registry.example.com"));
+        assert!(!is_local_url("not a url"));
+    }
+
+    #[test]
+    fn test_debug_build_to_remote_is_rejected_when_strict() {
+        let remote = "https://registry.example.com";
+        assert!(check_debug_target(&rust_build("debug"), remote, true).is_err());
+        assert!(check_debug_target(&rust_build("debug"), remote, false).is_ok());
+        assert!(check_debug_target(&rust_build("release"), remote, true).is_ok());
+        assert!(check_debug_target(&rust_build("debug"), "http://localhost:50001", true).is_ok());
+    }
+
+    #[test]
+    fn test_stream_buffer_size_from_env_value() {
+        assert_eq!(parse_stream_buffer(None), DEFAULT_STREAM_BUFFER_BYTES);
+        assert_eq!(parse_stream_buffer(Some("262144")), 262144);
+        assert_eq!(parse_stream_buffer(Some("0")), DEFAULT_STREAM_BUFFER_BYTES);
+        assert_eq!(parse_stream_buffer(Some("x")), DEFAULT_STREAM_BUFFER_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_frames_follow_the_stream_buffer() {
+        let (frame_tx, mut frame_rx) = mpsc::channel(2);
+        let (limit_tx, _limit_rx) = oneshot::channel();
+        read_frames(&[7u8; 10][..], 4, None, frame_tx, limit_tx).await;
+
+        let mut sizes = Vec::new();
+        while let Some(frame) = frame_rx.recv().await {
+            sizes.push(frame.data.len());
+        }
+        assert_eq!(sizes, [4, 4, 2]);
+    }
+
+    #[test]
+    fn test_artifact_limit() {
+        assert!(!exceeds_limit(u64::MAX, None));
+        assert!(!exceeds_limit(1024, Some(1024)));
+        assert!(exceeds_limit(1025, Some(1024)));
+    }
+
+    #[test]
+    fn test_text_result_is_tab_separated() {
+        assert_eq!(
+            format_result(OutputFormat::Text, "hello", "sha256:abc"),
+            "hello\tsha256:abc"
+        );
+    }
+
+    #[test]
+    fn test_json_result_has_key_and_digest() {
+        let value: serde_json::Value =
+            serde_json::from_str(&format_result(OutputFormat::Json, "hello", "sha256:abc"))
+                .unwrap();
+        assert_eq!(value["key"], "hello");
+        assert_eq!(value["digest"], "sha256:abc");
+    }
+
+    #[tokio::test]
+    async fn test_no_git_hash_outside_a_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(git_commit(dir.path(), true).await, None);
+        assert_eq!(git_commit(dir.path(), false).await, None);
+    }
+
+    #[test]
+    fn test_rejection_includes_reason() {
+        assert_eq!(
+            rejection_message(""),
+            "Control plane rejected digest to name mapping"
+        );
+        assert_eq!(
+            rejection_message("key 'hello' is locked"),
+            "Control plane rejected digest to name mapping: key 'hello' is locked"
+        );
+    }
+
+    #[test]
+    fn test_file_output_manifest_uses_entry_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("handler");
+        std::fs::write(&binary, "bin").unwrap();
+
+        let output = BuildOutput::File {
+            path: binary,
+            name: "bootstrap".to_string(),
+        };
+        assert_eq!(
+            output_manifest(dir.path(), &output, &ArchiveConfig::default()).unwrap(),
+            vec![ManifestEntry {
+                path: PathBuf::from("bootstrap"),
+                size: 3
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline_reports_active_phase() {
+        let phase = PhaseTracker::new();
+        let push = async {
+            phase.set(Phase::Uploading);
+            std::future::pending::<Result<()>>().await
+        };
+
+        let error = with_deadline(Some(Duration::from_millis(10)), &phase, push)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("uploading the artifact"));
+
+        let finished = with_deadline(Some(Duration::from_secs(5)), &phase, async { Ok(()) });
+        assert!(finished.await.is_ok());
+    }
+
+    #[test]
+    fn test_orphaned_digest_names_key_and_digest() {
+        let message = orphaned_digest_message("hello", "sha256:abc");
+        assert!(message.contains("sha256:abc was pushed"));
+        assert!(message.contains("key 'hello'"));
+    }
+
+    #[test]
+    fn test_up_to_date_requires_matching_input_tag() {
+        let tag = "inputs-0123";
+        assert!(inputs_unchanged("sha256:abc", tag, tag));
+        assert!(!inputs_unchanged("sha256:abc", "inputs-4567", tag));
+        assert!(!inputs_unchanged("sha256:abc", "", tag));
+        assert!(!inputs_unchanged("", tag, tag));
+
+        let json = format_up_to_date(OutputFormat::Json, "hello", "sha256:abc");
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["up_to_date"], true);
+
+        let text = format_up_to_date(OutputFormat::Text, "hello", "sha256:abc");
+        assert_eq!(text, "hello is up to date (sha256:abc), nothing to push");
+    }
+
+    #[test]
+    fn test_digests_compared_only_for_sha256() {
+        let client = format!("{}{:x}", DIGEST_PREFIX, Sha256::digest(b"artifact"));
+
+        assert_eq!(digests_match(&client, &client.to_uppercase()), Some(true));
+        assert_eq!(digests_match(&client, "sha256:abc"), Some(false));
+        assert_eq!(digests_match(&client, "blake3:abc"), None);
     }
 }
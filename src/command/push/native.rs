@@ -0,0 +1,276 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tokio::process::Command;
+use tonic::async_trait;
+use tracing::{debug, info};
+
+use super::{BuildService, make_executable, require_tool, run_with_timeout};
+
+/// Build system driving a C/C++ build
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildSystem {
+    Cmake,
+    Make,
+}
+
+impl BuildSystem {
+    fn program(self) -> &'static str {
+        match self {
+            BuildSystem::Cmake => "cmake",
+            BuildSystem::Make => "make",
+        }
+    }
+}
+
+/// Build a C or C++ project with CMake or Make and copy the produced executable
+#[derive(Debug, Deserialize)]
+pub struct NativeBuildConfig {
+    /// Build system, "cmake" or "make"
+    system: BuildSystem,
+
+    /// Build directory relative to the project path (default: "build" for cmake, "." for make)
+    #[serde(default)]
+    build_dir: Option<PathBuf>,
+
+    /// CMake generator passed as `-G` (e.g. "Ninja"), CMake's default when unset
+    #[serde(default)]
+    generator: Option<String>,
+
+    /// Executable produced by the build, relative to the project path
+    artifact: PathBuf,
+
+    /// Extra arguments appended to the build invocation
+    #[serde(default)]
+    extra_args: Vec<String>,
+
+    /// Name the executable is copied to in the artifact
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Timeout for each build step in seconds (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+}
+
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+impl NativeBuildConfig {
+    /// Validate the configuration and that the project has a CMakeLists.txt or Makefile
+    pub(super) fn validate(&self, project_path: &Path) -> anyhow::Result<()> {
+        if self.artifact.as_os_str().is_empty() {
+            bail!("Native build artifact cannot be empty");
+        }
+
+        if self.artifact.is_absolute() {
+            bail!(
+                "Native build artifact must be relative to the project path, got {:?}",
+                self.artifact
+            );
+        }
+
+        if self.timeout_seconds == 0 {
+            bail!("Timeout must be greater than 0");
+        }
+
+        match self.system {
+            BuildSystem::Cmake => {
+                if self.generator.as_ref().is_some_and(|g| g.trim().is_empty()) {
+                    bail!("CMake generator cannot be empty");
+                }
+
+                let lists = project_path.join("CMakeLists.txt");
+                if !lists.is_file() {
+                    bail!("No CMakeLists.txt found at {:?}", lists);
+                }
+            }
+            BuildSystem::Make => {
+                if self.generator.is_some() {
+                    bail!("generator is only supported for cmake builds");
+                }
+
+                let dir = project_path.join(self.build_dir());
+                let has_makefile = ["GNUmakefile", "makefile", "Makefile"]
+                    .iter()
+                    .any(|name| dir.join(name).is_file());
+                if !has_makefile {
+                    bail!("No Makefile found in {:?}", dir);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build directory, falling back to the system's default
+    fn build_dir(&self) -> PathBuf {
+        match (&self.build_dir, self.system) {
+            (Some(dir), _) => dir.clone(),
+            (None, BuildSystem::Cmake) => PathBuf::from("build"),
+            (None, BuildSystem::Make) => PathBuf::from("."),
+        }
+    }
+
+    /// Steps run in the project directory, each as a name and its arguments
+    fn steps(&self) -> Vec<(&'static str, Vec<String>)> {
+        let build_dir = self.build_dir().display().to_string();
+
+        match self.system {
+            BuildSystem::Cmake => {
+                let mut configure = vec![
+                    "-S".to_string(),
+                    ".".to_string(),
+                    "-B".to_string(),
+                    build_dir.clone(),
+                    "-DCMAKE_BUILD_TYPE=Release".to_string(),
+                ];
+                if let Some(ref generator) = self.generator {
+                    configure.push("-G".to_string());
+                    configure.push(generator.clone());
+                }
+
+                let mut build = vec!["--build".to_string(), build_dir];
+                // User supplied arguments go last so they can extend the generated flags
+                build.extend(self.extra_args.iter().cloned());
+
+                vec![("cmake configure", configure), ("cmake build", build)]
+            }
+            BuildSystem::Make => {
+                let mut build = vec!["-C".to_string(), build_dir];
+                build.extend(self.extra_args.iter().cloned());
+
+                vec![("make", build)]
+            }
+        }
+    }
+
+    /// Run one step in the project directory, failing on a non-zero exit
+    async fn run_step(
+        &self,
+        name: &str,
+        args: &[String],
+        project_path: &Path,
+    ) -> anyhow::Result<()> {
+        let mut cmd = Command::new(self.system.program());
+        cmd.args(args).current_dir(project_path);
+
+        debug!("Running {}: {:?}", name, args);
+        run_with_timeout(cmd, name, self.timeout_seconds).await
+    }
+}
+
+#[async_trait]
+impl BuildService for NativeBuildConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        self.validate(&project_path)
+            .context("Invalid native build configuration")?;
+        require_tool(self.system.program(), &["--version"]).await?;
+
+        info!("Building with {}", self.system.program());
+        for (name, args) in self.steps() {
+            self.run_step(name, &args, &project_path).await?;
+        }
+
+        let artifact = project_path.join(&self.artifact);
+        if !artifact.is_file() {
+            bail!(
+                "Build did not produce the artifact at {:?}, check the artifact setting",
+                artifact
+            );
+        }
+
+        let output_path = temp_path.join(&self.entrypoint);
+        info!("Copying {:?} as '{}'", artifact, self.entrypoint);
+        tokio::fs::copy(&artifact, &output_path)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to {:?}", artifact, output_path))?;
+
+        make_executable(&output_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(system: &str, extra: &str) -> NativeBuildConfig {
+        toml::from_str(&format!(
+            "system = \"{}\"\nartifact = \"build/handler\"\n{}",
+            system, extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let cmake = config("cmake", "");
+        assert_eq!(cmake.system, BuildSystem::Cmake);
+        assert_eq!(cmake.build_dir(), PathBuf::from("build"));
+        assert_eq!(cmake.generator, None);
+        assert_eq!(cmake.artifact, PathBuf::from("build/handler"));
+        assert_eq!(cmake.entrypoint, "bootstrap");
+        assert_eq!(cmake.timeout_seconds, 900);
+
+        assert_eq!(config("make", "").build_dir(), PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_cmake_steps() {
+        let config = config(
+            "cmake",
+            "build_dir = \"out\"\n\
+             generator = \"Ninja\"\n\
+             extra_args = [\"--parallel\", \"4\"]",
+        );
+
+        let steps = config.steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            steps[0].1,
+            vec![
+                "-S",
+                ".",
+                "-B",
+                "out",
+                "-DCMAKE_BUILD_TYPE=Release",
+                "-G",
+                "Ninja"
+            ]
+        );
+        assert_eq!(steps[1].1, vec!["--build", "out", "--parallel", "4"]);
+    }
+
+    #[test]
+    fn test_make_steps() {
+        let config = config("make", "extra_args = [\"-j4\", \"handler\"]");
+
+        let steps = config.steps();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].1, vec!["-C", ".", "-j4", "handler"]);
+    }
+
+    #[test]
+    fn test_validate_requires_build_files() {
+        let project_dir = tempfile::tempdir().unwrap();
+        assert!(config("cmake", "").validate(project_dir.path()).is_err());
+        assert!(config("make", "").validate(project_dir.path()).is_err());
+
+        std::fs::write(project_dir.path().join("CMakeLists.txt"), "").unwrap();
+        std::fs::write(project_dir.path().join("Makefile"), "").unwrap();
+        assert!(config("cmake", "").validate(project_dir.path()).is_ok());
+        assert!(config("make", "").validate(project_dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_generator_requires_cmake() {
+        let project_dir = tempfile::tempdir().unwrap();
+        std::fs::write(project_dir.path().join("Makefile"), "").unwrap();
+
+        let config = config("make", "generator = \"Ninja\"");
+        assert!(config.validate(project_dir.path()).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tokio::process::Command;
+use tonic::async_trait;
+use tracing::{debug, info};
+
+use super::{BuildService, make_executable, require_tool, run_with_timeout, shell_command};
+
+/// Package manager used to install dependencies
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    #[default]
+    Npm,
+    Pnpm,
+    Yarn,
+}
+
+impl PackageManager {
+    fn program(self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+        }
+    }
+
+    /// Arguments installing exactly the locked dependencies
+    fn install_args(self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Npm => &["ci"],
+            PackageManager::Pnpm => &["install", "--frozen-lockfile"],
+            PackageManager::Yarn => &["install", "--frozen-lockfile"],
+        }
+    }
+}
+
+/// Install dependencies and bundle a Node.js or TypeScript project into a single file
+#[derive(Debug, Deserialize)]
+pub struct NodeBuildConfig {
+    /// Package manager used for the install step
+    #[serde(default)]
+    package_manager: PackageManager,
+
+    /// Run the install step before building
+    #[serde(default = "default_install")]
+    install: bool,
+
+    /// Source file bundled by the default esbuild command
+    #[serde(default = "default_entry")]
+    entry: PathBuf,
+
+    /// Shell command producing `output`, instead of bundling `entry` with esbuild
+    #[serde(default)]
+    build_command: Option<String>,
+
+    /// Bundle written by the build, relative to the project path
+    #[serde(default = "default_output")]
+    output: PathBuf,
+
+    /// Name the bundle is copied to in the artifact
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Timeout for the install and build steps in seconds (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+}
+
+fn default_install() -> bool {
+    true
+}
+
+fn default_entry() -> PathBuf {
+    PathBuf::from("src/index.ts")
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("dist/index.js")
+}
+
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+impl NodeBuildConfig {
+    /// Validate the configuration and that the project has a `package.json`
+    pub(super) fn validate(&self, project_path: &Path) -> anyhow::Result<()> {
+        if self.timeout_seconds == 0 {
+            bail!("Timeout must be greater than 0");
+        }
+
+        if self
+            .build_command
+            .as_ref()
+            .is_some_and(|c| c.trim().is_empty())
+        {
+            bail!("Build command cannot be empty");
+        }
+
+        let package_json = project_path.join("package.json");
+        if !package_json.is_file() {
+            bail!("No package.json found at {:?}", package_json);
+        }
+
+        Ok(())
+    }
+
+    /// Arguments for the default esbuild bundle command
+    fn esbuild_args(&self) -> Vec<String> {
+        vec![
+            "--yes".to_string(),
+            "esbuild".to_string(),
+            self.entry.display().to_string(),
+            "--bundle".to_string(),
+            "--platform=node".to_string(),
+            format!("--outfile={}", self.output.display()),
+        ]
+    }
+
+    /// The build step, either the configured shell command or esbuild through `npx`
+    fn build_step(&self) -> Command {
+        match self.build_command {
+            Some(ref command) => shell_command(command),
+            None => {
+                let mut cmd = Command::new("npx");
+                cmd.args(self.esbuild_args());
+                cmd
+            }
+        }
+    }
+
+    /// Copy the built bundle into `temp_path` as the executable entrypoint
+    async fn copy_bundle(&self, project_path: &Path, temp_path: &Path) -> anyhow::Result<()> {
+        let bundle = project_path.join(&self.output);
+        if !bundle.is_file() {
+            bail!(
+                "Build did not produce the bundle at {:?}, check the output setting",
+                bundle
+            );
+        }
+
+        let output_path = temp_path.join(&self.entrypoint);
+        info!("Copying bundle {:?} as '{}'", bundle, self.entrypoint);
+        tokio::fs::copy(&bundle, &output_path)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to {:?}", bundle, output_path))?;
+
+        make_executable(&output_path).await
+    }
+
+    /// Run a step in the project directory, failing on a non-zero exit
+    async fn run_step(
+        &self,
+        name: &str,
+        mut cmd: Command,
+        project_path: &Path,
+    ) -> anyhow::Result<()> {
+        cmd.current_dir(project_path);
+        run_with_timeout(cmd, name, self.timeout_seconds).await
+    }
+}
+
+#[async_trait]
+impl BuildService for NodeBuildConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        self.validate(&project_path)
+            .context("Invalid node build configuration")?;
+        require_tool("node", &["--version"]).await?;
+
+        let package_manager = self.package_manager.program();
+        if self.install {
+            require_tool(package_manager, &["--version"]).await?;
+
+            info!("Installing dependencies with {}", package_manager);
+            let mut install = Command::new(package_manager);
+            install.args(self.package_manager.install_args());
+            self.run_step("Dependency install", install, &project_path)
+                .await?;
+        }
+
+        info!("Bundling project");
+        debug!("Build command: {:?}", self.build_command);
+        self.run_step("Build", self.build_step(), &project_path)
+            .await?;
+
+        self.copy_bundle(&project_path, &temp_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config: NodeBuildConfig = toml::from_str("").unwrap();
+
+        assert_eq!(config.package_manager, PackageManager::Npm);
+        assert!(config.install);
+        assert_eq!(config.entry, PathBuf::from("src/index.ts"));
+        assert_eq!(config.build_command, None);
+        assert_eq!(config.output, PathBuf::from("dist/index.js"));
+        assert_eq!(config.entrypoint, "bootstrap");
+        assert_eq!(config.timeout_seconds, 900);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: NodeBuildConfig = toml::from_str(
+            r#"
+            package_manager = "pnpm"
+            install = false
+            build_command = "pnpm run bundle"
+            output = "build/handler.js"
+            entrypoint = "handler.js"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.package_manager, PackageManager::Pnpm);
+        assert!(!config.install);
+        assert_eq!(config.build_command.as_deref(), Some("pnpm run bundle"));
+        assert_eq!(config.output, PathBuf::from("build/handler.js"));
+        assert_eq!(config.entrypoint, "handler.js");
+    }
+
+    #[test]
+    fn test_install_args() {
+        assert_eq!(PackageManager::Npm.install_args(), ["ci"]);
+        assert_eq!(
+            PackageManager::Yarn.install_args(),
+            ["install", "--frozen-lockfile"]
+        );
+    }
+
+    #[test]
+    fn test_esbuild_args() {
+        let config: NodeBuildConfig = toml::from_str(r#"entry = "src/main.ts""#).unwrap();
+
+        assert_eq!(
+            config.esbuild_args(),
+            vec![
+                "--yes",
+                "esbuild",
+                "src/main.ts",
+                "--bundle",
+                "--platform=node",
+                "--outfile=dist/index.js",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_bundle() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config: NodeBuildConfig = toml::from_str("").unwrap();
+
+        assert!(
+            config
+                .copy_bundle(project_dir.path(), temp_dir.path())
+                .await
+                .is_err()
+        );
+
+        std::fs::create_dir(project_dir.path().join("dist")).unwrap();
+        std::fs::write(project_dir.path().join("dist").join("index.js"), "bundle").unwrap();
+        config
+            .copy_bundle(project_dir.path(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let output = temp_dir.path().join("bootstrap");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "bundle");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&output).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn test_validate_requires_package_json() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let config: NodeBuildConfig = toml::from_str("").unwrap();
+        assert!(config.validate(project_dir.path()).is_err());
+
+        std::fs::write(project_dir.path().join("package.json"), "{}").unwrap();
+        assert!(config.validate(project_dir.path()).is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tonic::async_trait;
+use tracing::{debug, info};
+
+use super::{BuildService, compress_binary, make_executable};
+
+/// Package an existing binary or directory produced outside of the CLI
+#[derive(Debug, Deserialize)]
+pub struct PrebuiltConfig {
+    /// Binary or directory to package, relative to the project path
+    path: PathBuf,
+
+    /// Name a single binary is copied to in the artifact
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Compress a single binary with `upx --best` when `upx` is installed
+    #[serde(default)]
+    compress_binary: bool,
+}
+
+impl PrebuiltConfig {
+    /// Resolve the configured path and check it exists
+    pub(super) fn source_path(&self, project_path: &Path) -> anyhow::Result<PathBuf> {
+        let source = project_path.join(&self.path);
+        if !source.exists() {
+            bail!("Prebuilt artifact does not exist: {:?}", source);
+        }
+        Ok(source)
+    }
+}
+
+#[async_trait]
+impl BuildService for PrebuiltConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        let source = self.source_path(&project_path)?;
+
+        if source.is_dir() {
+            info!("Packaging prebuilt directory {:?}", source);
+            copy_dir(&source, &temp_path).await
+        } else {
+            info!(
+                "Packaging prebuilt binary {:?} as '{}'",
+                source, self.entrypoint
+            );
+            let output_path = temp_path.join(&self.entrypoint);
+            tokio::fs::copy(&source, &output_path)
+                .await
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, output_path))?;
+            make_executable(&output_path).await?;
+
+            if self.compress_binary {
+                compress_binary(&output_path).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Recursively copy the contents of `source` into `destination`
+async fn copy_dir(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    let mut pending = vec![(source.to_path_buf(), destination.to_path_buf())];
+
+    while let Some((from, to)) = pending.pop() {
+        tokio::fs::create_dir_all(&to)
+            .await
+            .with_context(|| format!("Failed to create {:?}", to))?;
+
+        let mut entries = tokio::fs::read_dir(&from)
+            .await
+            .with_context(|| format!("Failed to read {:?}", from))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .with_context(|| format!("Failed to read entry in {:?}", from))?
+        {
+            let path = entry.path();
+            let target = to.join(entry.file_name());
+
+            if path.is_dir() {
+                pending.push((path, target));
+            } else {
+                debug!("Copying {:?}", path);
+                tokio::fs::copy(&path, &target)
+                    .await
+                    .with_context(|| format!("Failed to copy {:?} to {:?}", path, target))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(path: &str) -> PrebuiltConfig {
+        toml::from_str(&format!("path = \"{}\"", path)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_prebuilt_binary() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(project_dir.path().join("dist")).unwrap();
+        std::fs::write(project_dir.path().join("dist").join("handler"), "bin").unwrap();
+
+        config("dist/handler")
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+
+        let output = temp_dir.path().join("bootstrap");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "bin");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&output).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prebuilt_directory() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dist = project_dir.path().join("dist");
+        std::fs::create_dir_all(dist.join("lib")).unwrap();
+        std::fs::write(dist.join("bootstrap"), "bin").unwrap();
+        std::fs::write(dist.join("lib").join("data"), "data").unwrap();
+
+        config("dist")
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+
+        assert!(temp_dir.path().join("bootstrap").is_file());
+        assert!(temp_dir.path().join("lib").join("data").is_file());
+    }
+
+    #[tokio::test]
+    async fn test_prebuilt_missing_path() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let result = config("missing")
+            .build(
+                project_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+}
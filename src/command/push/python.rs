@@ -0,0 +1,362 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Deserialize;
+use tokio::process::Command;
+use tonic::async_trait;
+use tracing::{debug, info};
+
+use super::{BuildService, require_tool, run_with_timeout, shell_command};
+
+/// Install dependencies into the output and copy the handler source next to them
+#[derive(Debug, Deserialize)]
+pub struct PythonBuildConfig {
+    /// Python interpreter used for the pip install
+    #[serde(default = "default_python")]
+    python: String,
+
+    /// Requirements file relative to the project path, `requirements.txt` when present if unset
+    #[serde(default)]
+    requirements: Option<PathBuf>,
+
+    /// Shell command installing dependencies into `$OUTPUT`, instead of pip
+    #[serde(default)]
+    install_command: Option<String>,
+
+    /// Gitignore style globs selecting the source files copied into the output
+    #[serde(default = "default_include")]
+    include: Vec<String>,
+
+    /// Gitignore style globs for source files and directories that are never copied
+    #[serde(default = "default_exclude")]
+    exclude: Vec<String>,
+
+    /// Timeout for the install step in seconds (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+}
+
+fn default_python() -> String {
+    if cfg!(target_os = "windows") {
+        "python".to_string()
+    } else {
+        "python3".to_string()
+    }
+}
+
+fn default_include() -> Vec<String> {
+    vec!["*.py".to_string()]
+}
+
+fn default_exclude() -> Vec<String> {
+    [".venv/", "venv/", "__pycache__/", ".git/", "tests/"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+impl PythonBuildConfig {
+    /// Validate the configuration and that the requirements file exists
+    pub(super) fn validate(&self, project_path: &Path) -> anyhow::Result<()> {
+        if self.python.trim().is_empty() {
+            bail!("Python interpreter cannot be empty");
+        }
+
+        if self
+            .install_command
+            .as_ref()
+            .is_some_and(|c| c.trim().is_empty())
+        {
+            bail!("Install command cannot be empty");
+        }
+
+        if self.include.is_empty() {
+            bail!("Python build include cannot be empty");
+        }
+
+        if self.timeout_seconds == 0 {
+            bail!("Timeout must be greater than 0");
+        }
+
+        if let Some(ref requirements) = self.requirements {
+            let path = project_path.join(requirements);
+            if !path.is_file() {
+                bail!("No requirements file found at {:?}", path);
+            }
+        }
+
+        glob_matcher(project_path, &self.include).context("Invalid include pattern")?;
+        glob_matcher(project_path, &self.exclude).context("Invalid exclude pattern")?;
+
+        Ok(())
+    }
+
+    /// Requirements file to install, if any
+    fn requirements_path(&self, project_path: &Path) -> Option<PathBuf> {
+        match self.requirements {
+            Some(ref requirements) => Some(requirements.clone()),
+            None => {
+                let default = PathBuf::from("requirements.txt");
+                project_path.join(&default).is_file().then_some(default)
+            }
+        }
+    }
+
+    /// Arguments for `python -m pip install` into `temp_path`
+    fn pip_args(&self, requirements: &Path, temp_path: &Path) -> Vec<String> {
+        vec![
+            "-m".to_string(),
+            "pip".to_string(),
+            "install".to_string(),
+            "--requirement".to_string(),
+            requirements.display().to_string(),
+            "--target".to_string(),
+            temp_path.display().to_string(),
+        ]
+    }
+
+    /// The install step, either the configured shell command or pip, if there is one to run
+    fn install_step(&self, project_path: &Path, temp_path: &Path) -> Option<Command> {
+        if let Some(ref command) = self.install_command {
+            let mut cmd = shell_command(command);
+            cmd.env("OUTPUT", temp_path);
+            return Some(cmd);
+        }
+
+        let requirements = self.requirements_path(project_path)?;
+        let mut cmd = Command::new(&self.python);
+        cmd.args(self.pip_args(&requirements, temp_path));
+        Some(cmd)
+    }
+
+    /// Run the install step in the project directory, failing on a non-zero exit
+    async fn run_install(&self, mut cmd: Command, project_path: &Path) -> anyhow::Result<()> {
+        cmd.current_dir(project_path);
+        run_with_timeout(cmd, "Dependency install", self.timeout_seconds).await
+    }
+
+    /// Source files below `project_path` matching `include` and not `exclude`, as
+    /// paths relative to the project
+    fn source_files(&self, project_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let include = glob_matcher(project_path, &self.include)?;
+        let exclude = glob_matcher(project_path, &self.exclude)?;
+
+        let mut files = Vec::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(dir) = pending.pop() {
+            let absolute = project_path.join(&dir);
+            let read_dir = std::fs::read_dir(&absolute)
+                .with_context(|| format!("Failed to read {:?}", absolute))?;
+
+            for entry in read_dir {
+                let entry =
+                    entry.with_context(|| format!("Failed to read entry in {:?}", absolute))?;
+                let relative = dir.join(entry.file_name());
+                let is_dir = entry
+                    .file_type()
+                    .with_context(|| format!("Failed to read file type of {:?}", relative))?
+                    .is_dir();
+
+                if exclude.matched(&relative, is_dir).is_ignore() {
+                    debug!("Excluding {:?}", relative);
+                    continue;
+                }
+
+                if is_dir {
+                    pending.push(relative);
+                } else if include
+                    .matched_path_or_any_parents(&relative, false)
+                    .is_ignore()
+                {
+                    files.push(relative);
+                }
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Match paths relative to `root` against gitignore style `patterns`
+fn glob_matcher(root: &Path, patterns: &[String]) -> anyhow::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("Invalid glob '{}'", pattern))?;
+    }
+
+    builder.build().context("Failed to build glob matcher")
+}
+
+#[async_trait]
+impl BuildService for PythonBuildConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        self.validate(&project_path)
+            .context("Invalid python build configuration")?;
+
+        match self.install_step(&project_path, &temp_path) {
+            Some(install) => {
+                if self.install_command.is_none() {
+                    require_tool(&self.python, &["--version"]).await?;
+                    require_tool(&self.python, &["-m", "pip", "--version"]).await?;
+                }
+
+                info!("Installing dependencies into the output");
+                self.run_install(install, &project_path).await?;
+            }
+            None => info!("No requirements file, skipping dependency install"),
+        }
+
+        let files = self.source_files(&project_path)?;
+        if files.is_empty() {
+            bail!(
+                "No source files matched include {:?}, check the include setting",
+                self.include
+            );
+        }
+
+        info!("Copying {} source files", files.len());
+        for relative in files {
+            let source = project_path.join(&relative);
+            let destination = temp_path.join(&relative);
+            if let Some(parent) = destination.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context(|| format!("Failed to create {:?}", parent))?;
+            }
+
+            debug!("Copying {:?}", relative);
+            tokio::fs::copy(&source, &destination)
+                .await
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, destination))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config: PythonBuildConfig = toml::from_str("").unwrap();
+
+        assert_eq!(config.requirements, None);
+        assert_eq!(config.install_command, None);
+        assert_eq!(config.include, vec!["*.py"]);
+        assert!(config.exclude.contains(&".venv/".to_string()));
+        assert_eq!(config.timeout_seconds, 900);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: PythonBuildConfig = toml::from_str(
+            r#"
+            python = "python3.12"
+            requirements = "requirements/prod.txt"
+            include = ["handler/", "*.json"]
+            exclude = ["handler/tests/"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.python, "python3.12");
+        assert_eq!(
+            config.requirements,
+            Some(PathBuf::from("requirements/prod.txt"))
+        );
+        assert_eq!(config.include, vec!["handler/", "*.json"]);
+        assert_eq!(config.exclude, vec!["handler/tests/"]);
+    }
+
+    #[test]
+    fn test_pip_args() {
+        let config: PythonBuildConfig = toml::from_str("").unwrap();
+
+        assert_eq!(
+            config.pip_args(Path::new("requirements.txt"), Path::new("/tmp/out")),
+            vec![
+                "-m",
+                "pip",
+                "install",
+                "--requirement",
+                "requirements.txt",
+                "--target",
+                "/tmp/out",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requirements_default_when_present() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let config: PythonBuildConfig = toml::from_str("").unwrap();
+        assert_eq!(config.requirements_path(project_dir.path()), None);
+
+        write(project_dir.path(), "requirements.txt");
+        assert_eq!(
+            config.requirements_path(project_dir.path()),
+            Some(PathBuf::from("requirements.txt"))
+        );
+    }
+
+    #[test]
+    fn test_validate_missing_requirements() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let config: PythonBuildConfig = toml::from_str(r#"requirements = "deps.txt""#).unwrap();
+        assert!(config.validate(project_dir.path()).is_err());
+
+        write(project_dir.path(), "deps.txt");
+        assert!(config.validate(project_dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_source_files_include_exclude() {
+        let project_dir = tempfile::tempdir().unwrap();
+        for file in [
+            "main.py",
+            "handler/app.py",
+            "handler/tests/test_app.py",
+            "handler/config.json",
+            ".venv/lib/site.py",
+            "README.md",
+        ] {
+            write(project_dir.path(), file);
+        }
+
+        let config: PythonBuildConfig = toml::from_str("").unwrap();
+        assert_eq!(
+            config.source_files(project_dir.path()).unwrap(),
+            vec![PathBuf::from("handler/app.py"), PathBuf::from("main.py")]
+        );
+
+        let config: PythonBuildConfig = toml::from_str(
+            r#"
+            include = ["handler/"]
+            exclude = ["tests/"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.source_files(project_dir.path()).unwrap(),
+            vec![
+                PathBuf::from("handler/app.py"),
+                PathBuf::from("handler/config.json")
+            ]
+        );
+    }
+}
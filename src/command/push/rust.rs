@@ -1,21 +1,64 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
+use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
 use tokio::fs;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
+use tokio::task::JoinSet;
 use tonic::async_trait;
-use tracing::debug;
+use tracing::{debug, info, warn};
 
-use super::BuildService;
+use super::env::{restrict_env, validate_pass_env};
+use super::{BuildOutput, BuildService, compress_binary, make_executable};
 
-#[derive(Deserialize, Debug)]
+/// One or more target triples
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TargetSpec {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl TargetSpec {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            TargetSpec::Single(target) => vec![target],
+            TargetSpec::Multiple(targets) => targets,
+        }
+    }
+}
+
+/// Tool that runs the build
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Builder {
+    #[default]
+    Cargo,
+    /// `cross`, building inside a container with the target toolchain
+    Cross,
+}
+
+impl Builder {
+    fn program(self) -> &'static str {
+        match self {
+            Builder::Cargo => "cargo",
+            Builder::Cross => "cross",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct RustBuildConfig {
-    /// Target triple (e.g., "x86_64-unknown-linux-musl")
+    /// Target triple (e.g., "x86_64-unknown-linux-musl"), or a list of triples
     #[serde(default)]
-    target: Option<String>,
+    target: Option<TargetSpec>,
 
-    /// Build profile: "release" or "debug"
+    /// Build profile: "release", "debug" or the name of a custom `[profile.*]`
     #[serde(default = "default_profile")]
     profile: String,
 
@@ -26,30 +69,214 @@ pub struct RustBuildConfig {
     /// Expected binary name
     #[serde(default)]
     binary_name: Option<String>,
+
+    /// Binaries packaged as `bin/<name>`, by name or `*` pattern (instead of `binary_name`)
+    #[serde(default)]
+    binaries: Vec<String>,
+
+    /// Cargo features to enable
+    #[serde(default)]
+    features: Vec<String>,
+
+    /// Disable the default features of the package
+    #[serde(default)]
+    no_default_features: bool,
+
+    /// Enable all available features
+    #[serde(default)]
+    all_features: bool,
+
+    /// Name of the binary inside the artifact
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Install the target with rustup when it is missing
+    #[serde(default)]
+    auto_install_target: bool,
+
+    /// Strip symbols from the binary (defaults to false for debug, true otherwise)
+    #[serde(default)]
+    strip: Option<bool>,
+
+    /// Timeout for `cargo build` in seconds (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+
+    /// Value for `RUSTFLAGS` when running cargo
+    #[serde(default)]
+    rustflags: Option<String>,
+
+    /// Extra arguments appended to the cargo build invocation
+    #[serde(default)]
+    extra_args: Vec<String>,
+
+    /// Pass `--offline` to cargo, for air-gapped builds
+    #[serde(default)]
+    offline: bool,
+
+    /// Compress the packaged binary with `upx --best` when `upx` is installed
+    #[serde(default)]
+    compress_binary: bool,
+
+    /// Build with "cargo" (default) or "cross"
+    #[serde(default)]
+    builder: Builder,
+
+    /// Path of each packaged binary, e.g. "{target}/bootstrap" or "bootstrap-{arch}",
+    /// using `{target}`, `{arch}`, `{os}`, `{binary}` and `{profile}`
+    #[serde(default)]
+    output_template: Option<String>,
+
+    /// Fail when a built binary cannot be found (default: true). When disabled, a missing
+    /// binary is only a warning and the executable cargo reported is packaged instead
+    #[serde(default = "default_verify_build")]
+    verify_build: bool,
+
+    /// Host variables forwarded to cargo (besides PATH), instead of the whole environment.
+    /// Unset inherits everything; list e.g. HOME, CARGO_HOME and RUSTUP_HOME when needed
+    #[serde(default)]
+    pass_env: Option<Vec<String>>,
+
+    /// Render a progress line instead of cargo's output, decided by the command line
+    #[serde(skip)]
+    progress: bool,
+
+    /// `cargo metadata` fetched ahead of the build, see [`RustBuildConfig::load_metadata`]
+    #[serde(skip)]
+    metadata: Option<Arc<CargoMetadata>>,
 }
 
 fn default_profile() -> String {
     "release".to_string()
 }
 
-impl From<RustBuildConfig> for RustBuild {
-    fn from(config: RustBuildConfig) -> Self {
-        let profile = match config.profile.to_lowercase().as_str() {
-            "debug" => BuildProfile::Debug,
-            "release" => BuildProfile::Release,
-            _ => {
-                debug!(
-                    "Unknown profile '{}', defaulting to Release",
-                    config.profile
-                );
-                BuildProfile::Release
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+fn default_verify_build() -> bool {
+    true
+}
+
+impl RustBuildConfig {
+    /// Replace the configured package, binary and target with the given values
+    pub(super) fn override_with(
+        &mut self,
+        package: Option<String>,
+        binary: Option<String>,
+        target: Option<String>,
+    ) {
+        if package.is_some() {
+            self.package_name = package;
+        }
+        if binary.is_some() {
+            self.binary_name = binary;
+            self.binaries.clear();
+        }
+        if let Some(target) = target {
+            self.target = Some(TargetSpec::Single(target));
+        }
+    }
+
+    /// Downgrade a missing built binary from an error to a warning
+    pub(super) fn skip_verify_build(&mut self) {
+        self.verify_build = false;
+    }
+
+    /// Show a crates compiled progress line instead of cargo's raw output
+    pub(super) fn show_progress(&mut self, enabled: bool) {
+        self.progress = enabled;
+    }
+
+    /// Run `cargo metadata` once, so resolving and building the project reuse its output
+    pub(super) async fn load_metadata(&mut self, project_path: &Path) -> anyhow::Result<()> {
+        let metadata = get_metadata(project_path, self.offline, self.pass_env.as_deref()).await?;
+        self.metadata = Some(Arc::new(metadata));
+        Ok(())
+    }
+
+    /// Whether this config builds with the debug profile
+    pub(super) fn is_debug(&self) -> bool {
+        BuildProfile::from_name(&self.profile) == BuildProfile::Debug
+    }
+
+    /// Create the build service, building every target when several are configured
+    pub fn into_build_service(mut self) -> anyhow::Result<Box<dyn BuildService + Send + Sync>> {
+        let targets = self
+            .target
+            .take()
+            .map(TargetSpec::into_vec)
+            .unwrap_or_default();
+        let build = RustBuild::try_from(self)?;
+
+        match <[String; 1]>::try_from(targets) {
+            Ok([target]) => Ok(Box::new(build.target(target))),
+            Err(targets) if targets.is_empty() => Ok(Box::new(build)),
+            Err(targets) => {
+                check_distinct_outputs(&build, &targets)?;
+                Ok(Box::new(MultiTargetRustBuild::new(build, targets)))
             }
-        };
+        }
+    }
+}
+
+/// Package and binary target a Rust build resolves to
+#[derive(Debug)]
+pub struct ResolvedBinary {
+    pub package: String,
+    pub binary: String,
+}
+
+impl RustBuildConfig {
+    /// Check the config and resolve the package and binary without building
+    pub async fn resolve(mut self, project_path: &Path) -> anyhow::Result<ResolvedBinary> {
+        // Every target builds the same binary, so only a single build needs resolving
+        self.target.take();
+        let build = RustBuild::try_from(self)?;
+
+        build.resolve(project_path).await
+    }
+}
+
+impl TryFrom<RustBuildConfig> for RustBuild {
+    type Error = anyhow::Error;
+
+    fn try_from(config: RustBuildConfig) -> anyhow::Result<Self> {
+        if config.timeout_seconds == 0 {
+            anyhow::bail!("Build timeout must be greater than 0 seconds");
+        }
+
+        if config.all_features && !config.features.is_empty() {
+            anyhow::bail!(
+                "'all_features' and 'features' cannot both be set (features: {:?})",
+                config.features
+            );
+        }
+
+        if config.binary_name.is_some() && !config.binaries.is_empty() {
+            anyhow::bail!("'binary_name' and 'binaries' cannot both be set");
+        }
 
-        let mut builder = RustBuild::new().profile(profile);
+        let profile = BuildProfile::from_name(&config.profile);
+
+        let strip = config
+            .strip
+            .unwrap_or(!matches!(profile, BuildProfile::Debug));
+        let mut builder = RustBuild::new()
+            .profile(profile)
+            .strip(strip)
+            .timeout(Duration::from_secs(config.timeout_seconds));
 
         if let Some(target) = config.target {
-            builder = builder.target(target);
+            match <[String; 1]>::try_from(target.into_vec()) {
+                Ok([target]) => builder = builder.target(target),
+                Err(targets) if targets.is_empty() => {}
+                Err(targets) => anyhow::bail!(
+                    "Expected a single target but {} were configured: {:?}",
+                    targets.len(),
+                    targets
+                ),
+            }
         }
 
         if let Some(package_name) = config.package_name {
@@ -60,25 +287,60 @@ impl From<RustBuildConfig> for RustBuild {
             builder = builder.binary_name(binary_name);
         }
 
-        builder
+        if let Some(rustflags) = config.rustflags {
+            builder = builder.rustflags(rustflags);
+        }
+
+        if let Some(pass_env) = config.pass_env {
+            validate_pass_env(&pass_env)?;
+            builder = builder.pass_env(pass_env);
+        }
+
+        if let Some(template) = config.output_template {
+            validate_output_template(&template, !config.binaries.is_empty())?;
+            builder = builder.output_template(template);
+        }
+
+        if let Some(metadata) = config.metadata {
+            builder = builder.metadata(metadata);
+        }
+
+        Ok(builder
+            .entrypoint(config.entrypoint)
+            .binaries(config.binaries)
+            .auto_install_target(config.auto_install_target)
+            .features(config.features)
+            .no_default_features(config.no_default_features)
+            .all_features(config.all_features)
+            .extra_args(config.extra_args)
+            .offline(config.offline)
+            .compress(config.compress_binary)
+            .builder(config.builder)
+            .verify_build(config.verify_build)
+            .progress(config.progress))
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 struct CargoMetadata {
     packages: Vec<Package>,
+    /// Build output directory, honoring `CARGO_TARGET_DIR`, cargo config and workspaces
+    #[serde(default)]
+    target_directory: Option<PathBuf>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 struct Package {
+    #[serde(default)]
+    id: String,
     name: String,
     manifest_path: String,
     #[serde(default)]
     targets: Vec<Target>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 struct Target {
     name: String,
     kind: Vec<String>,
@@ -91,7 +353,7 @@ pub struct RustBuild {
     /// If None, uses the default target
     pub target: Option<String>,
 
-    /// Build profile (release or debug)
+    /// Build profile (release, debug or a named profile)
     pub profile: BuildProfile,
 
     /// Expected package name (if None, uses workspace root or first package)
@@ -99,12 +361,99 @@ pub struct RustBuild {
 
     /// Expected binary name (if None, finds first binary target)
     pub binary_name: Option<String>,
+
+    /// Binary names or `*` patterns packaged under `bin/` instead of the entrypoint
+    pub binaries: Vec<String>,
+
+    /// Cargo features to enable
+    pub features: Vec<String>,
+
+    /// Pass `--no-default-features` to cargo
+    pub no_default_features: bool,
+
+    /// Pass `--all-features` to cargo
+    pub all_features: bool,
+
+    /// Name the binary is copied to in the output directory
+    pub entrypoint: String,
+
+    /// Run `rustup target add` when the target is not installed
+    pub auto_install_target: bool,
+
+    /// Have cargo strip symbols from the built binary
+    pub strip: bool,
+
+    /// Maximum time `cargo build` may run before it is killed
+    pub timeout: Duration,
+
+    /// `RUSTFLAGS` passed to cargo
+    pub rustflags: Option<String>,
+
+    /// Arguments appended after the generated cargo flags
+    pub extra_args: Vec<String>,
+
+    /// Pass `--offline` to `cargo metadata` and `cargo build`
+    pub offline: bool,
+
+    /// Run `upx` on the copied binary
+    pub compress: bool,
+
+    /// Tool invoked for the build
+    pub builder: Builder,
+
+    /// Template for the packaged binary's path, replacing the entrypoint and `bin/` layout
+    pub output_template: Option<String>,
+
+    /// Fail when a built binary cannot be found instead of packaging what cargo reported
+    pub verify_build: bool,
+
+    /// Host variables cargo sees, all of them when `None`
+    pub pass_env: Option<Vec<String>>,
+
+    /// Render a crates compiled progress line instead of cargo's raw output
+    pub progress: bool,
+
+    /// Metadata fetched ahead of the build, used instead of running `cargo metadata`
+    metadata: Option<Arc<CargoMetadata>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BuildProfile {
     Release,
     Debug,
+    /// A custom `[profile.<name>]` from Cargo.toml
+    Named(String),
+}
+
+impl BuildProfile {
+    /// Parse a profile name, treating `debug`/`dev` and `release` as the built-in profiles
+    fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "debug" | "dev" => BuildProfile::Debug,
+            "release" => BuildProfile::Release,
+            _ => {
+                debug!("Using custom cargo profile '{}'", name);
+                BuildProfile::Named(name.to_string())
+            }
+        }
+    }
+
+    /// Directory below `target/` that cargo writes this profile's output to
+    fn dir_name(&self) -> &str {
+        match self {
+            BuildProfile::Release => "release",
+            BuildProfile::Debug => "debug",
+            BuildProfile::Named(name) => name,
+        }
+    }
+
+    /// Name of the profile in cargo's `[profile]` table
+    fn cargo_name(&self) -> &str {
+        match self {
+            BuildProfile::Debug => "dev",
+            profile => profile.dir_name(),
+        }
+    }
 }
 
 impl Default for RustBuild {
@@ -114,6 +463,24 @@ impl Default for RustBuild {
             profile: BuildProfile::Release,
             package_name: None,
             binary_name: None,
+            binaries: Vec::new(),
+            features: Vec::new(),
+            no_default_features: false,
+            all_features: false,
+            entrypoint: super::default_entrypoint(),
+            auto_install_target: false,
+            strip: true,
+            timeout: Duration::from_secs(default_timeout()),
+            rustflags: None,
+            extra_args: Vec::new(),
+            offline: false,
+            compress: false,
+            builder: Builder::Cargo,
+            output_template: None,
+            verify_build: true,
+            pass_env: None,
+            progress: false,
+            metadata: None,
         }
     }
 }
@@ -147,6 +514,114 @@ impl RustBuild {
         self.binary_name = Some(name.into());
         self
     }
+
+    /// Package the matching binaries under `bin/`
+    pub fn binaries(mut self, patterns: Vec<String>) -> Self {
+        self.binaries = patterns;
+        self
+    }
+
+    /// Set the cargo features to enable
+    pub fn features(mut self, features: Vec<String>) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Disable default features
+    pub fn no_default_features(mut self, enabled: bool) -> Self {
+        self.no_default_features = enabled;
+        self
+    }
+
+    /// Enable all features
+    pub fn all_features(mut self, enabled: bool) -> Self {
+        self.all_features = enabled;
+        self
+    }
+
+    /// Set the name of the binary in the output directory
+    pub fn entrypoint(mut self, name: impl Into<String>) -> Self {
+        self.entrypoint = name.into();
+        self
+    }
+
+    /// Install a missing target with rustup
+    pub fn auto_install_target(mut self, enabled: bool) -> Self {
+        self.auto_install_target = enabled;
+        self
+    }
+
+    /// Strip symbols from the built binary
+    pub fn strip(mut self, enabled: bool) -> Self {
+        self.strip = enabled;
+        self
+    }
+
+    /// Set the cargo build timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set `RUSTFLAGS` for the cargo invocation
+    pub fn rustflags(mut self, rustflags: impl Into<String>) -> Self {
+        self.rustflags = Some(rustflags.into());
+        self
+    }
+
+    /// Set extra arguments appended to the cargo invocation
+    pub fn extra_args(mut self, args: Vec<String>) -> Self {
+        self.extra_args = args;
+        self
+    }
+
+    /// Run cargo without network access
+    pub fn offline(mut self, enabled: bool) -> Self {
+        self.offline = enabled;
+        self
+    }
+
+    /// Compress the packaged binary with `upx`
+    pub fn compress(mut self, enabled: bool) -> Self {
+        self.compress = enabled;
+        self
+    }
+
+    /// Set the tool invoked for the build
+    pub fn builder(mut self, builder: Builder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    /// Forward only these host variables (and PATH) to cargo
+    pub fn pass_env(mut self, names: Vec<String>) -> Self {
+        self.pass_env = Some(names);
+        self
+    }
+
+    /// Set whether a missing built binary fails the build
+    pub fn verify_build(mut self, enabled: bool) -> Self {
+        self.verify_build = enabled;
+        self
+    }
+
+    /// Set the template for the packaged binary's path
+    pub fn output_template(mut self, template: impl Into<String>) -> Self {
+        self.output_template = Some(template.into());
+        self
+    }
+
+    /// Set whether to render a progress line instead of cargo's raw output
+    pub fn progress(mut self, enabled: bool) -> Self {
+        self.progress = enabled;
+        self
+    }
+
+    /// Use already fetched metadata instead of running `cargo metadata`
+    fn metadata(mut self, metadata: Arc<CargoMetadata>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 }
 
 #[async_trait]
@@ -156,31 +631,125 @@ impl BuildService for RustBuild {
         self.validate_project(&project_path).await?;
 
         // Get package metadata
-        let metadata = get_metadata(&project_path).await?;
+        let metadata = self.load_metadata(&project_path).await?;
 
-        // Find the target package
-        let package = self.find_package(&metadata, &project_path)?;
+        self.build_with_metadata(&project_path, &temp_path, &metadata)
+            .await
+    }
 
-        // Find the binary target
-        let binary_target = self.find_binary_target(package)?;
+    async fn build_output(
+        &self,
+        project_path: PathBuf,
+        temp_path: PathBuf,
+    ) -> anyhow::Result<BuildOutput> {
+        if !self.archives_in_place() {
+            self.build(project_path, temp_path.clone()).await?;
+            return Ok(BuildOutput::Directory(temp_path));
+        }
 
-        // Run cargo build
-        self.run_cargo_build(&project_path).await?;
+        self.validate_project(&project_path).await?;
+        let metadata = self.load_metadata(&project_path).await?;
+
+        let mut binaries = self.compile(&project_path, &metadata).await?;
+        let (_, binary_path) = binaries.pop().context("Build did not resolve a binary")?;
+        info!(
+            "Archiving {:?} in place as '{}'",
+            binary_path, self.entrypoint
+        );
+
+        Ok(BuildOutput::File {
+            path: binary_path,
+            name: self.entrypoint.clone(),
+        })
+    }
+}
 
-        // Determine binary path
-        let binary_path = self.get_binary_path(&project_path, &binary_target.name);
+impl RustBuild {
+    /// The metadata fetched ahead of the build, or a fresh `cargo metadata` run
+    async fn load_metadata(&self, project_path: &Path) -> anyhow::Result<Arc<CargoMetadata>> {
+        match self.metadata {
+            Some(ref metadata) => Ok(Arc::clone(metadata)),
+            None => Ok(Arc::new(
+                get_metadata(project_path, self.offline, self.pass_env.as_deref()).await?,
+            )),
+        }
+    }
 
-        // Validate binary exists
-        self.validate_binary_exists(&binary_path).await?;
+    /// Whether the built binary can be archived straight from the target directory,
+    /// which needs a single binary packaged under the entrypoint without modification
+    fn archives_in_place(&self) -> bool {
+        self.binaries.is_empty() && self.output_template.is_none() && !self.compress
+    }
 
-        // Copy binary to output
-        self.copy_binary(&binary_path, &temp_path).await?;
+    /// Build the binary into `temp_path` using already fetched metadata
+    async fn build_with_metadata(
+        &self,
+        project_path: &Path,
+        temp_path: &Path,
+        metadata: &CargoMetadata,
+    ) -> anyhow::Result<()> {
+        for (name, binary_path) in &self.compile(project_path, metadata).await? {
+            // Copy binary to output
+            let output_path = self.output_path(temp_path, name)?;
+            copy_binary(binary_path, &output_path).await?;
+
+            if self.compress {
+                compress_binary(&output_path).await?;
+            }
+        }
 
         Ok(())
     }
-}
 
-impl RustBuild {
+    /// Run cargo and return the name and path of every built binary
+    async fn compile(
+        &self,
+        project_path: &Path,
+        metadata: &CargoMetadata,
+    ) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        // Find the target package
+        let package = self.find_package(metadata, project_path)?;
+
+        // Find the binary targets
+        let binary_targets = self.find_binary_targets(package)?;
+
+        // Determine binary paths
+        let target_directory = self.target_directory(metadata, project_path);
+        debug!("Using target directory: {:?}", target_directory);
+        let binaries: Vec<(String, PathBuf)> = binary_targets
+            .iter()
+            .map(|t| {
+                (
+                    t.name.clone(),
+                    self.get_binary_path(&target_directory, &t.name),
+                )
+            })
+            .collect();
+
+        for (name, binary_path) in &binaries {
+            info!(
+                package = %package.name,
+                binary = %name,
+                target_triple = self.target.as_deref().unwrap_or("host"),
+                profile = self.profile.dir_name(),
+                expected_path = ?binary_path,
+                "Resolved build plan"
+            );
+        }
+
+        // Run cargo build
+        let progress = self.build_progress(project_path, &package.id).await;
+        let executables = self.run_cargo_build(project_path, &progress).await?;
+
+        binaries
+            .into_iter()
+            .map(|(name, expected)| {
+                let path = locate_binary(&name, expected, &executables, self.verify_build)?;
+                Ok((name, path))
+            })
+            .collect()
+    }
+
     /// Validate that the project has required files and cargo is available
     async fn validate_project(&self, project_path: &Path) -> anyhow::Result<()> {
         // Check if Cargo.toml exists
@@ -198,9 +767,88 @@ impl RustBuild {
             );
         }
 
+        // Verify the target is installed, cross brings its own toolchains
+        if self.builder == Builder::Cross {
+            let cross_check = Command::new("cross").arg("--version").output().await;
+            if cross_check.is_err() {
+                anyhow::bail!(
+                    "cross command not found. Install it with `cargo install cross` \
+                    or set builder = \"cargo\""
+                );
+            }
+        } else if let Some(ref target) = self.target {
+            self.ensure_target_installed(project_path, target).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the target is installed with rustup, installing it if enabled
+    async fn ensure_target_installed(
+        &self,
+        project_path: &Path,
+        target: &str,
+    ) -> anyhow::Result<()> {
+        let mut list = Command::new("rustup");
+        list.args(["target", "list", "--installed"])
+            .current_dir(project_path);
+        restrict_env(&mut list, self.pass_env.as_deref());
+        let output = list.output().await;
+
+        let installed = match output {
+            Ok(output) if output.status.success() => output.stdout,
+            _ => {
+                debug!("rustup is not available, skipping target check");
+                return Ok(());
+            }
+        };
+
+        if is_target_installed(&String::from_utf8_lossy(&installed), target) {
+            return Ok(());
+        }
+
+        if !self.auto_install_target {
+            anyhow::bail!(
+                "Target '{}' is not installed. Run `rustup target add {}` \
+                or set `auto_install_target = true` in the build config.",
+                target,
+                target
+            );
+        }
+
+        info!("Installing missing target '{}' with rustup", target);
+        let mut add = Command::new("rustup");
+        add.args(["target", "add", target])
+            .current_dir(project_path);
+        restrict_env(&mut add, self.pass_env.as_deref());
+        let status = add
+            .status()
+            .await
+            .context("Failed to run rustup target add")?;
+
+        if !status.success() {
+            anyhow::bail!("rustup target add {} failed", target);
+        }
+
         Ok(())
     }
 
+    /// Resolve the package and binary target from `cargo metadata`
+    pub async fn resolve(&self, project_path: &Path) -> anyhow::Result<ResolvedBinary> {
+        let metadata = self.load_metadata(project_path).await?;
+        let package = self.find_package(&metadata, project_path)?;
+        let binary_targets = self.find_binary_targets(package)?;
+
+        Ok(ResolvedBinary {
+            package: package.name.clone(),
+            binary: binary_targets
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+
     /// Find the target package in the metadata
     fn find_package<'a>(
         &self,
@@ -216,33 +864,83 @@ impl RustBuild {
                 .with_context(|| format!("Package '{}' not found in workspace", name));
         }
 
-        // Try to find package at the project root
+        // Try to find package at the project root, falling back to the first package
         let cargo_toml_path = project_path.join("Cargo.toml");
-        if let Some(package) = metadata
+        let package = metadata
             .packages
             .iter()
             .find(|p| Path::new(&p.manifest_path) == cargo_toml_path)
-        {
+            .or_else(|| metadata.packages.first())
+            .context("No packages found in cargo metadata. Is this a valid Rust project?")?;
+
+        if self.find_binary_targets(package).is_ok() {
             return Ok(package);
         }
 
-        // Fall back to first package
-        metadata
-            .packages
-            .first()
-            .context("No packages found in cargo metadata. Is this a valid Rust project?")
+        self.find_binary_package(metadata, package)
     }
 
-    /// Find the binary target in the package
-    fn find_binary_target<'a>(&self, package: &'a Package) -> anyhow::Result<&'a Target> {
-        // If binary name is specified, find by name
-        if let Some(ref name) = self.binary_name {
-            return package
-                .targets
-                .iter()
-                .find(|t| t.kind.contains(&"bin".to_string()) && t.name == *name)
-                .with_context(|| {
-                    format!(
+    /// Find the only workspace package providing the binary when the detected package does not
+    fn find_binary_package<'a>(
+        &self,
+        metadata: &'a CargoMetadata,
+        detected: &Package,
+    ) -> anyhow::Result<&'a Package> {
+        let candidates: Vec<&Package> = metadata
+            .packages
+            .iter()
+            .filter(|p| self.find_binary_targets(p).is_ok())
+            .collect();
+
+        match candidates.as_slice() {
+            [package] => {
+                info!(
+                    "Package '{}' has no matching binary target, using '{}' instead",
+                    detected.name, package.name
+                );
+                Ok(package)
+            }
+            [] => anyhow::bail!(
+                "No binary targets found in any workspace package. Packages: {:?}",
+                metadata
+                    .packages
+                    .iter()
+                    .map(|p| &p.name)
+                    .collect::<Vec<_>>()
+            ),
+            _ => {
+                let listed: Vec<String> = candidates
+                    .iter()
+                    .map(|p| {
+                        let bins: Vec<&str> = p
+                            .targets
+                            .iter()
+                            .filter(|t| t.kind.contains(&"bin".to_string()))
+                            .map(|t| t.name.as_str())
+                            .collect();
+                        format!("{} ({})", p.name, bins.join(", "))
+                    })
+                    .collect();
+                anyhow::bail!(
+                    "Package '{}' has no matching binary target and several workspace packages \
+                    do. Set package_name to one of: {}",
+                    detected.name,
+                    listed.join("; ")
+                )
+            }
+        }
+    }
+
+    /// Find the binary target in the package
+    fn find_binary_target<'a>(&self, package: &'a Package) -> anyhow::Result<&'a Target> {
+        // If binary name is specified, find by name
+        if let Some(ref name) = self.binary_name {
+            return package
+                .targets
+                .iter()
+                .find(|t| t.kind.contains(&"bin".to_string()) && t.name == *name)
+                .with_context(|| {
+                    format!(
                         "Binary target '{}' not found in package '{}'",
                         name, package.name
                     )
@@ -263,40 +961,264 @@ impl RustBuild {
             })
     }
 
-    /// Run cargo build command
-    async fn run_cargo_build(&self, project_path: &Path) -> anyhow::Result<()> {
-        let mut cmd = Command::new("cargo");
-        cmd.arg("build");
+    /// Find every binary target matching `binaries`, or the single binary target when unset
+    fn find_binary_targets<'a>(&self, package: &'a Package) -> anyhow::Result<Vec<&'a Target>> {
+        if self.binaries.is_empty() {
+            return Ok(vec![self.find_binary_target(package)?]);
+        }
+
+        let bins: Vec<&Target> = package
+            .targets
+            .iter()
+            .filter(|t| t.kind.contains(&"bin".to_string()))
+            .collect();
+
+        let mut targets: Vec<&Target> = Vec::new();
+        for pattern in &self.binaries {
+            let matched: Vec<&Target> = bins
+                .iter()
+                .copied()
+                .filter(|t| matches_pattern(pattern, &t.name))
+                .collect();
+
+            if matched.is_empty() {
+                anyhow::bail!(
+                    "No binary target matching '{}' in package '{}'. Available binaries: {:?}",
+                    pattern,
+                    package.name,
+                    bins.iter().map(|t| &t.name).collect::<Vec<_>>()
+                );
+            }
+
+            for target in matched {
+                if !targets.iter().any(|t| t.name == target.name) {
+                    targets.push(target);
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Build the argument list passed to cargo
+    fn cargo_build_args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
 
         // Add profile argument
         match self.profile {
-            BuildProfile::Release => {
-                cmd.arg("--release");
-            }
+            BuildProfile::Release => args.push("--release".to_string()),
             BuildProfile::Debug => {
                 // Debug is default, no flag needed
             }
+            BuildProfile::Named(ref name) => {
+                args.push("--profile".to_string());
+                args.push(name.clone());
+            }
+        }
+
+        // Strip while linking so the binary in the target directory can be archived as is
+        if self.strip {
+            args.push("--config".to_string());
+            args.push(format!("profile.{}.strip=true", self.profile.cargo_name()));
         }
 
         // Add target if specified
         if let Some(ref target) = self.target {
-            cmd.arg("--target").arg(target);
+            args.push("--target".to_string());
+            args.push(target.clone());
+        }
+
+        // Add feature flags
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+
+        if self.offline {
+            args.push("--offline".to_string());
+        }
+
+        // User supplied arguments go last so they can extend the generated flags
+        args.extend(self.extra_args.iter().cloned());
+
+        args
+    }
+
+    /// Create the cargo build command for the project, reporting artifacts as JSON on stdout
+    fn cargo_build_command(&self, project_path: &Path) -> Command {
+        let mut cmd = Command::new(self.builder.program());
+        cmd.args(self.cargo_build_args())
+            .arg("--message-format=json-render-diagnostics");
+        // The progress line replaces cargo's status output, diagnostics are still rendered
+        if self.progress {
+            cmd.arg("--quiet");
+        }
+        restrict_env(&mut cmd, self.pass_env.as_deref());
+
+        if let Some(ref rustflags) = self.rustflags {
+            debug!("Using RUSTFLAGS: {}", rustflags);
+            cmd.env("RUSTFLAGS", rustflags);
         }
 
         cmd.current_dir(project_path)
-            .stdout(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit());
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::inherit())
+            .kill_on_drop(true);
 
-        let status = cmd.status().await.with_context(|| {
-            format!(
-                "Failed to execute cargo build in directory: {:?}",
-                project_path
-            )
-        })?;
+        cmd
+    }
+
+    /// Flags of the build that restrict dependency resolution, e.g. `--locked`
+    fn resolve_flags(&self) -> Vec<&str> {
+        let mut flags: Vec<&str> = self
+            .extra_args
+            .iter()
+            .map(String::as_str)
+            .filter(|arg| matches!(*arg, "--locked" | "--frozen" | "--offline"))
+            .collect();
+        if self.offline && !flags.contains(&"--offline") {
+            flags.push("--offline");
+        }
+
+        flags
+    }
+
+    /// Progress line for the cargo build, hidden unless progress mode is on
+    async fn build_progress(&self, project_path: &Path, package_id: &str) -> ProgressBar {
+        if !self.progress {
+            return ProgressBar::hidden();
+        }
+
+        let total = match self.crate_count(project_path, package_id).await {
+            Ok(total) => Some(total),
+            Err(e) => {
+                debug!("Could not count the crates to build: {:#}", e);
+                None
+            }
+        };
+
+        build_progress_bar(total)
+    }
+
+    /// Number of packages compiled for `package_id`, from the resolved dependency graph.
+    /// The `--no-deps` metadata used to resolve the binary has no graph, so this runs a
+    /// full dependency resolution, only in progress mode.
+    async fn crate_count(&self, project_path: &Path, package_id: &str) -> anyhow::Result<u64> {
+        let mut cmd = Command::new(self.builder.program());
+        cmd.arg("metadata")
+            .arg("--format-version=1")
+            .args(self.resolve_flags())
+            .current_dir(project_path);
+        if let Some(ref target) = self.target {
+            cmd.arg("--filter-platform").arg(target);
+        }
+        restrict_env(&mut cmd, self.pass_env.as_deref());
+
+        let output = cmd.output().await.context("Failed to run cargo metadata")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("cargo metadata failed: {}", stderr);
+        }
+
+        let metadata: ResolvedMetadata =
+            serde_json::from_slice(&output.stdout).context("Failed to parse cargo metadata")?;
+        let resolve = metadata
+            .resolve
+            .context("cargo metadata did not resolve dependencies")?;
+
+        Ok(count_build_units(&resolve.nodes, package_id) as u64)
+    }
+
+    /// Run cargo build command, returning the executables cargo reported by target name
+    async fn run_cargo_build(
+        &self,
+        project_path: &Path,
+        progress: &ProgressBar,
+    ) -> anyhow::Result<HashMap<String, PathBuf>> {
+        let mut child = self
+            .cargo_build_command(project_path)
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "Failed to execute cargo build in directory: {:?}",
+                    project_path
+                )
+            })?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("cargo build stdout was not captured")?;
+
+        let run = async {
+            let mut executables = HashMap::new();
+            let mut compiled = HashSet::new();
+            let mut lines = BufReader::new(stdout).lines();
+            while let Some(line) = lines.next_line().await? {
+                let Some(artifact) = parse_artifact(&line) else {
+                    continue;
+                };
+
+                // A package reports an artifact per target, so count each package once
+                if compiled.insert(artifact.package_id.clone()) {
+                    progress.inc(1);
+                    progress.set_message(artifact.target.name.clone());
+                }
+
+                if let Some((name, path)) = artifact.into_executable() {
+                    debug!("cargo built '{}' at {:?}", name, path);
+                    executables.insert(name, path);
+                }
+            }
+
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, executables))
+        };
+
+        debug!("Timeout: {}s", self.timeout.as_secs());
+        let result = tokio::time::timeout(self.timeout, run).await;
+        progress.finish_and_clear();
+
+        let (status, executables) = result
+            .with_context(|| {
+                format!(
+                    "cargo build timed out after {} seconds. \
+                    Consider increasing timeout_seconds or checking for a stalled cargo lock.",
+                    self.timeout.as_secs()
+                )
+            })?
+            .with_context(|| {
+                format!(
+                    "Failed to execute cargo build in directory: {:?}",
+                    project_path
+                )
+            })?;
 
         if !status.success() {
+            let cross_target = self.target.as_deref().filter(|target| {
+                self.builder == Builder::Cargo
+                    && is_cross_compile(target, std::env::consts::ARCH, std::env::consts::OS)
+            });
+            if let Some(target) = cross_target {
+                warn!(
+                    "Building for '{}' from a {}-{} host may need a cross toolchain. \
+                    Try `cargo install cross` and set builder = \"cross\" in the build config",
+                    target,
+                    std::env::consts::ARCH,
+                    std::env::consts::OS
+                );
+            }
+
             anyhow::bail!(
-                "cargo build failed with exit code: {}",
+                "{} build failed with exit code: {}",
+                self.builder.program(),
                 status
                     .code()
                     .map(|c| c.to_string())
@@ -304,70 +1226,487 @@ impl RustBuild {
             );
         }
 
-        Ok(())
+        Ok(executables)
     }
 
-    /// Get the path where the binary should be located
-    fn get_binary_path(&self, project_path: &Path, binary_name: &str) -> PathBuf {
-        let mut path = project_path.join("target");
+    /// Directory cargo writes build output to: a `--target-dir` in `extra_args`, else the
+    /// one reported by `cargo metadata`, else `project_path/target`
+    fn target_directory(&self, metadata: &CargoMetadata, project_path: &Path) -> PathBuf {
+        let from_args = self.extra_args.iter().enumerate().find_map(|(i, arg)| {
+            match arg.strip_prefix("--target-dir") {
+                Some("") => self.extra_args.get(i + 1).map(PathBuf::from),
+                Some(value) => value.strip_prefix('=').map(PathBuf::from),
+                None => None,
+            }
+        });
+
+        match from_args.or_else(|| metadata.target_directory.clone()) {
+            Some(dir) if dir.is_relative() => project_path.join(dir),
+            Some(dir) => dir,
+            None => project_path.join("target"),
+        }
+    }
+
+    /// Get the path where the binary should be located below cargo's target directory
+    fn get_binary_path(&self, target_directory: &Path, binary_name: &str) -> PathBuf {
+        let mut path = target_directory.to_path_buf();
 
         // Add target triple directory if specified
         if let Some(ref target) = self.target {
             path = path.join(target);
         }
 
-        // Add profile directory
-        match self.profile {
-            BuildProfile::Release => path = path.join("release"),
-            BuildProfile::Debug => path = path.join("debug"),
+        // Add profile directory and binary name
+        path.join(self.profile.dir_name()).join(binary_name)
+    }
+
+    /// Where a binary is packaged: the expanded `output_template`, else `bin/<name>` with
+    /// `binaries`, else the entrypoint
+    fn output_path(&self, temp_path: &Path, binary_name: &str) -> anyhow::Result<PathBuf> {
+        if let Some(ref template) = self.output_template {
+            let path = expand_template(template, |name| self.template_value(name, binary_name))?;
+            return Ok(temp_path.join(path));
         }
 
-        // Add binary name
-        path.join(binary_name)
+        if self.binaries.is_empty() {
+            Ok(temp_path.join(&self.entrypoint))
+        } else {
+            Ok(temp_path.join("bin").join(binary_name))
+        }
     }
 
-    /// Validate that the binary exists after build
-    async fn validate_binary_exists(&self, binary_path: &Path) -> anyhow::Result<()> {
-        if !binary_path.exists() {
-            anyhow::bail!(
-                "Expected binary not found at {:?}. Build may have completed but binary is missing. \
-                This could indicate a build configuration issue.",
-                binary_path
-            );
+    /// Value of an `output_template` variable for this build and binary
+    fn template_value(&self, name: &str, binary_name: &str) -> Option<String> {
+        let target = self.target.as_deref();
+        let value = match name {
+            "target" => target.unwrap_or("host"),
+            "arch" => target.map_or(std::env::consts::ARCH, |t| t.split('-').next().unwrap_or(t)),
+            "os" => target.map_or(std::env::consts::OS, |t| {
+                t.split('-').nth(2).unwrap_or("unknown")
+            }),
+            "binary" => binary_name,
+            "profile" => self.profile.dir_name(),
+            _ => return None,
+        };
+
+        Some(value.to_string())
+    }
+}
+
+/// Copy the binary to the output location and make it executable
+async fn copy_binary(binary_path: &Path, output_path: &Path) -> anyhow::Result<()> {
+    // Ensure parent directory exists
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create output directory: {:?}", parent))?;
+    }
+
+    // Copy the binary
+    fs::copy(binary_path, output_path).await.with_context(|| {
+        format!(
+            "Failed to copy binary from {:?} to {:?}",
+            binary_path, output_path
+        )
+    })?;
+
+    // Ensure the worker can execute it
+    make_executable(output_path).await
+}
+
+/// `compiler-artifact` message printed by `cargo build --message-format=json`
+#[derive(Deserialize)]
+struct ArtifactMessage {
+    reason: String,
+    #[serde(default)]
+    package_id: String,
+    target: ArtifactTarget,
+    #[serde(default)]
+    executable: Option<PathBuf>,
+}
+
+impl ArtifactMessage {
+    /// Binary target name and executable path, if the artifact is a binary
+    fn into_executable(self) -> Option<(String, PathBuf)> {
+        if !self.target.kind.iter().any(|k| k == "bin") {
+            return None;
         }
-        Ok(())
+
+        Some((self.target.name, self.executable?))
     }
+}
+
+#[derive(Deserialize)]
+struct ArtifactTarget {
+    name: String,
+    #[serde(default)]
+    kind: Vec<String>,
+}
+
+/// The `compiler-artifact` message on a cargo JSON output line, if it is one
+fn parse_artifact(line: &str) -> Option<ArtifactMessage> {
+    let message: ArtifactMessage = serde_json::from_str(line).ok()?;
+    (message.reason == "compiler-artifact").then_some(message)
+}
+
+/// `cargo metadata` output including the resolved dependency graph
+#[derive(Deserialize)]
+struct ResolvedMetadata {
+    #[serde(default)]
+    resolve: Option<Resolve>,
+}
 
-    /// Copy the binary to the output location
-    async fn copy_binary(&self, binary_path: &Path, temp_path: &Path) -> anyhow::Result<()> {
-        let output_path = temp_path.join("bootstrap");
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<ResolveNode>,
+}
 
-        // Ensure parent directory exists
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .with_context(|| format!("Failed to create output directory: {:?}", parent))?;
+#[derive(Deserialize)]
+struct ResolveNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<NodeDep>,
+}
+
+#[derive(Deserialize)]
+struct NodeDep {
+    pkg: String,
+    #[serde(default)]
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Deserialize)]
+struct DepKind {
+    /// "dev", "build" or `None` for a normal dependency
+    kind: Option<String>,
+}
+
+/// Packages reachable from `root` through normal and build dependencies, `root` included
+fn count_build_units(nodes: &[ResolveNode], root: &str) -> usize {
+    let nodes: HashMap<&str, &ResolveNode> = nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut seen = HashSet::from([root]);
+    let mut pending = vec![root];
+
+    while let Some(id) = pending.pop() {
+        let Some(node) = nodes.get(id) else {
+            continue;
+        };
+
+        for dep in &node.deps {
+            let dev_only = !dep.dep_kinds.is_empty()
+                && dep
+                    .dep_kinds
+                    .iter()
+                    .all(|k| k.kind.as_deref() == Some("dev"));
+            if !dev_only && seen.insert(dep.pkg.as_str()) {
+                pending.push(dep.pkg.as_str());
+            }
+        }
+    }
+
+    seen.len()
+}
+
+/// Bar of crates compiled out of `total`, or a counting spinner when the total is unknown
+fn build_progress_bar(total: Option<u64>) -> ProgressBar {
+    let (progress, template) = match total {
+        Some(total) => (
+            ProgressBar::new(total),
+            "{spinner} Compiling [{bar:30}] {pos}/{len} crates {msg}",
+        ),
+        None => (
+            ProgressBar::new_spinner(),
+            "{spinner} Compiling {pos} crates {msg}",
+        ),
+    };
+
+    progress.set_style(ProgressStyle::with_template(template).expect("valid progress template"));
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
+
+/// Path of the built binary `name`: the executable cargo reported, else the path expected
+/// below the target directory. Builders like `cross` report paths inside their container,
+/// so a reported path is only used when it exists. Without `verify`, a missing binary
+/// falls back to the only executable cargo reported.
+fn locate_binary(
+    name: &str,
+    expected: PathBuf,
+    executables: &HashMap<String, PathBuf>,
+    verify: bool,
+) -> anyhow::Result<PathBuf> {
+    if let Some(path) = executables.get(name).filter(|path| path.exists()) {
+        debug!(
+            "Using the executable cargo reported for '{}': {:?}",
+            name, path
+        );
+        return Ok(path.clone());
+    }
+
+    if expected.exists() {
+        debug!(
+            "cargo reported no executable for '{}', using the expected path {:?}",
+            name, expected
+        );
+        return Ok(expected);
+    }
+
+    if verify {
+        anyhow::bail!(
+            "Expected binary not found at {:?}. Build may have completed but binary is missing. \
+            This could indicate a build configuration issue, use --no-verify-build to \
+            package the executable cargo reported instead.",
+            expected
+        );
+    }
+
+    warn!(
+        "Expected binary not found at {:?}, looking for the executable cargo reported",
+        expected
+    );
+    let reported: Vec<&PathBuf> = executables.values().filter(|path| path.exists()).collect();
+    match <[&PathBuf; 1]>::try_from(reported) {
+        Ok([path]) => {
+            warn!("Packaging {:?} as binary '{}'", path, name);
+            Ok(path.clone())
+        }
+        Err(reported) => anyhow::bail!(
+            "Binary '{}' not found and cargo reported {} executables: {:?}",
+            name,
+            reported.len(),
+            reported
+        ),
+    }
+}
+
+/// Expand `{name}` variables in an output template with `lookup`
+fn expand_template(
+    template: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            anyhow::bail!("Unmatched '}}' in output_template '{}'", template);
         }
 
-        // Copy the binary
-        fs::copy(binary_path, &output_path).await.with_context(|| {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed '{{' in output_template '{}'", template))?;
+        let name = &rest[start + 1..start + end];
+        let value = lookup(name).with_context(|| {
             format!(
-                "Failed to copy binary from {:?} to {:?}",
-                binary_path, output_path
+                "Unknown variable '{{{}}}' in output_template '{}', expected one of {}",
+                name,
+                template,
+                TEMPLATE_VARIABLES.map(|v| format!("{{{}}}", v)).join(", ")
             )
         })?;
 
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+/// Variables available in `output_template`
+const TEMPLATE_VARIABLES: [&str; 5] = ["target", "arch", "os", "binary", "profile"];
+
+/// Check an output template only uses known variables and stays inside the artifact
+fn validate_output_template(template: &str, multiple_binaries: bool) -> anyhow::Result<()> {
+    let path = expand_template(template, |name| {
+        TEMPLATE_VARIABLES.contains(&name).then(|| name.to_string())
+    })?;
+
+    let path = Path::new(&path);
+    if path.as_os_str().is_empty() || path.is_absolute() {
+        anyhow::bail!(
+            "output_template must be a relative path, got '{}'",
+            template
+        );
+    }
+
+    if path
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        anyhow::bail!("output_template cannot contain '..', got '{}'", template);
+    }
+
+    if multiple_binaries && !template.contains("{binary}") {
+        anyhow::bail!(
+            "output_template '{}' must contain {{binary}} when 'binaries' is set",
+            template
+        );
+    }
+
+    Ok(())
+}
+
+/// Check every target packages its binary at a different path
+fn check_distinct_outputs(build: &RustBuild, targets: &[String]) -> anyhow::Result<()> {
+    if build.output_template.is_none() {
+        return Ok(());
+    }
+
+    let mut seen: Vec<PathBuf> = Vec::new();
+    for target in targets {
+        let path = build
+            .clone()
+            .target(target.clone())
+            .output_path(Path::new(""), &build.entrypoint)?;
+        if seen.contains(&path) {
+            anyhow::bail!(
+                "output_template produces {:?} for more than one target, \
+                include {{target}} or {{arch}}",
+                path
+            );
+        }
+        seen.push(path);
+    }
+
+    Ok(())
+}
+
+/// Match a binary name against a pattern where `*` matches any run of characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => name.strip_prefix(prefix).is_some_and(|name| {
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| matches_pattern(rest, &name[i..]))
+        }),
+    }
+}
+
+/// Whether `target` has a different architecture or OS than the host
+fn is_cross_compile(target: &str, host_arch: &str, host_os: &str) -> bool {
+    let arch = target.split('-').next().unwrap_or_default();
+    let os = match host_os {
+        "macos" => "darwin",
+        os => os,
+    };
+    arch != host_arch || !target.contains(os)
+}
+
+/// Check whether `rustup target list --installed` output contains the target
+fn is_target_installed(installed: &str, target: &str) -> bool {
+    installed.lines().any(|line| line.trim() == target)
+}
+
+/// Rust build producing one binary per target, each in its own subdirectory
+#[derive(Debug, Clone)]
+pub struct MultiTargetRustBuild {
+    /// Output subdirectory name and build for each target
+    builds: Vec<(String, RustBuild)>,
+}
+
+impl MultiTargetRustBuild {
+    /// Create a build for each target based on the given build
+    pub fn new(base: RustBuild, targets: Vec<String>) -> Self {
+        let names = target_dir_names(&targets);
+        let builds = names
+            .into_iter()
+            .zip(targets)
+            // Concurrent builds would draw over each other's progress line
+            .map(|(name, target)| (name, base.clone().target(target).progress(false)))
+            .collect();
+
+        Self { builds }
+    }
+}
+
+/// Name the output subdirectory after the target architecture, falling back to
+/// the full triple when architectures collide
+fn target_dir_names(targets: &[String]) -> Vec<String> {
+    let arches: Vec<String> = targets
+        .iter()
+        .map(|t| t.split('-').next().unwrap_or(t).to_string())
+        .collect();
+
+    let unique = arches
+        .iter()
+        .enumerate()
+        .all(|(i, arch)| !arches[..i].contains(arch));
+
+    if unique { arches } else { targets.to_vec() }
+}
+
+#[async_trait]
+impl BuildService for MultiTargetRustBuild {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        // Validate every target up front so missing targets are installed one at a time
+        for (_, build) in &self.builds {
+            build.validate_project(&project_path).await?;
+        }
+
+        // All targets share the same packages, so metadata is only fetched once
+        let Some((_, first)) = self.builds.first() else {
+            return Ok(());
+        };
+        let metadata = first.load_metadata(&project_path).await?;
+        let mut tasks = JoinSet::new();
+
+        for (name, build) in self.builds.clone() {
+            let project_path = project_path.clone();
+            let metadata = Arc::clone(&metadata);
+            // A template lays out the targets itself, otherwise each gets a subdirectory
+            let output_path = match build.output_template {
+                Some(_) => temp_path.clone(),
+                None => temp_path.join(&name),
+            };
+            let target = build.target.clone().unwrap_or_default();
+
+            tasks.spawn(async move {
+                fs::create_dir_all(&output_path).await.with_context(|| {
+                    format!("Failed to create output directory: {:?}", output_path)
+                })?;
+
+                info!("Building target '{}' into {:?}", target, output_path);
+                build
+                    .build_with_metadata(&project_path, &output_path, &metadata)
+                    .await
+                    .with_context(|| format!("Build for target '{}' failed", target))
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            result.context("Build task panicked")??;
+        }
+
         Ok(())
     }
 }
 
-/// Get cargo metadata for a project
-async fn get_metadata(project_path: &Path) -> anyhow::Result<CargoMetadata> {
-    let output = Command::new("cargo")
-        .arg("metadata")
+/// Create the `cargo metadata` command for a project, limited to the `pass_env` variables
+fn metadata_command(project_path: &Path, offline: bool, pass_env: Option<&[String]>) -> Command {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("metadata")
         .arg("--no-deps")
         .arg("--format-version=1")
-        .current_dir(project_path)
+        .current_dir(project_path);
+    restrict_env(&mut cmd, pass_env);
+
+    if offline {
+        cmd.arg("--offline");
+    }
+
+    cmd
+}
+
+/// Get cargo metadata for a project
+async fn get_metadata(
+    project_path: &Path,
+    offline: bool,
+    pass_env: Option<&[String]>,
+) -> anyhow::Result<CargoMetadata> {
+    let output = metadata_command(project_path, offline, pass_env)
         .output()
         .await
         .with_context(|| {
@@ -390,6 +1729,8 @@ async fn get_metadata(project_path: &Path) -> anyhow::Result<CargoMetadata> {
 
 #[cfg(test)]
 mod tests {
+    use std::ffi::OsStr;
+
     use super::*;
 
     #[test]
@@ -413,5 +1754,800 @@ mod tests {
         assert!(matches!(build.profile, BuildProfile::Release));
         assert_eq!(build.package_name, None);
         assert_eq!(build.binary_name, None);
+        assert!(build.features.is_empty());
+        assert_eq!(build.entrypoint, "bootstrap");
+    }
+
+    #[test]
+    fn test_entrypoint_from_config() {
+        let config: RustBuildConfig = toml::from_str(r#"entrypoint = "handler""#).unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.entrypoint, "handler");
+    }
+
+    #[test]
+    fn test_target_config_single() {
+        let config: RustBuildConfig =
+            toml::from_str(r#"target = "aarch64-unknown-linux-musl""#).unwrap();
+        assert_eq!(
+            config.target,
+            Some(TargetSpec::Single("aarch64-unknown-linux-musl".to_string()))
+        );
+
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.target, Some("aarch64-unknown-linux-musl".to_string()));
+    }
+
+    #[test]
+    fn test_target_config_multiple() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"target = ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.target,
+            Some(TargetSpec::Multiple(vec![
+                "x86_64-unknown-linux-musl".to_string(),
+                "aarch64-unknown-linux-musl".to_string()
+            ]))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_copy_binary_sets_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let source_dir = tempfile::tempdir().unwrap();
+        let binary_path = source_dir.path().join("my-binary");
+        std::fs::write(&binary_path, "binary").unwrap();
+        std::fs::set_permissions(&binary_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_path = RustBuild::new()
+            .output_path(temp_dir.path(), "my-binary")
+            .unwrap();
+        copy_binary(&binary_path, &output_path).await.unwrap();
+
+        assert_eq!(output_path, temp_dir.path().join("bootstrap"));
+        let mode = std::fs::metadata(&output_path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_cargo_build_extra_args_last() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            features = ["tls"]
+            extra_args = ["--locked", "--offline"]
+            "#,
+        )
+        .unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+
+        assert_eq!(
+            build.cargo_build_args(),
+            vec![
+                "build",
+                "--release",
+                "--config",
+                "profile.release.strip=true",
+                "--target",
+                "x86_64-unknown-linux-musl",
+                "--features",
+                "tls",
+                "--locked",
+                "--offline"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_flags() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            offline = true
+            extra_args = ["--locked", "--jobs", "2"]
+            "#,
+        )
+        .unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+
+        assert_eq!(build.resolve_flags(), vec!["--locked", "--offline"]);
+    }
+
+    #[test]
+    fn test_cargo_build_command_rustflags() {
+        let config: RustBuildConfig =
+            toml::from_str(r#"rustflags = "-C target-cpu=native""#).unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+
+        let cmd = build.cargo_build_command(Path::new("."));
+        let rustflags = cmd
+            .as_std()
+            .get_envs()
+            .find(|(key, _)| *key == "RUSTFLAGS")
+            .and_then(|(_, value)| value);
+        assert_eq!(rustflags, Some(OsStr::new("-C target-cpu=native")));
+
+        let cmd = RustBuild::new().cargo_build_command(Path::new("."));
+        assert!(cmd.as_std().get_envs().all(|(key, _)| key != "RUSTFLAGS"));
+    }
+
+    #[test]
+    fn test_offline_flag() {
+        let config: RustBuildConfig = toml::from_str("offline = true").unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+        assert!(build.cargo_build_args().contains(&"--offline".to_string()));
+
+        let args = |offline| {
+            metadata_command(Path::new("."), offline, None)
+                .as_std()
+                .get_args()
+                .any(|arg| arg == "--offline")
+        };
+        assert!(args(true));
+        assert!(!args(false));
+        assert!(
+            !RustBuild::new()
+                .cargo_build_args()
+                .contains(&"--offline".to_string())
+        );
+    }
+
+    #[test]
+    fn test_timeout_from_config() {
+        let config: RustBuildConfig = toml::from_str("").unwrap();
+        assert_eq!(config.timeout_seconds, 900);
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.timeout, Duration::from_secs(900));
+
+        let config: RustBuildConfig = toml::from_str("timeout_seconds = 60").unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.timeout, Duration::from_secs(60));
+
+        let config: RustBuildConfig = toml::from_str("timeout_seconds = 0").unwrap();
+        assert!(RustBuild::try_from(config).is_err());
+    }
+
+    #[test]
+    fn test_is_cross_compile() {
+        assert!(!is_cross_compile(
+            "x86_64-unknown-linux-musl",
+            "x86_64",
+            "linux"
+        ));
+        assert!(is_cross_compile(
+            "x86_64-unknown-linux-musl",
+            "aarch64",
+            "macos"
+        ));
+        assert!(is_cross_compile(
+            "aarch64-unknown-linux-gnu",
+            "x86_64",
+            "linux"
+        ));
+        assert!(!is_cross_compile(
+            "aarch64-apple-darwin",
+            "aarch64",
+            "macos"
+        ));
+    }
+
+    #[test]
+    fn test_metadata_command_pass_env() {
+        let cmd = metadata_command(Path::new("."), false, None);
+        assert_eq!(cmd.as_std().get_envs().count(), 0);
+
+        let pass_env = ["NOCTI_UNSET_TEST_VAR".to_string()];
+        let cmd = metadata_command(Path::new("."), false, Some(&pass_env));
+        assert!(cmd.as_std().get_envs().all(|(key, _)| key == "PATH"));
+    }
+
+    #[test]
+    fn test_pass_env_keeps_rustflags() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            rustflags = "-C target-cpu=native"
+            pass_env = ["NOCTI_UNSET_TEST_VAR"]
+            "#,
+        )
+        .unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+
+        let cmd = build.cargo_build_command(Path::new("."));
+        let names: Vec<_> = cmd.as_std().get_envs().map(|(key, _)| key).collect();
+        assert!(names.contains(&OsStr::new("RUSTFLAGS")));
+        assert!(!names.contains(&OsStr::new("NOCTI_UNSET_TEST_VAR")));
+
+        let config: RustBuildConfig = toml::from_str(r#"pass_env = [""]"#).unwrap();
+        assert!(RustBuild::try_from(config).is_err());
+    }
+
+    #[test]
+    fn test_progress_quiets_cargo() {
+        let has_quiet = |build: &RustBuild| {
+            build
+                .cargo_build_command(Path::new("."))
+                .as_std()
+                .get_args()
+                .any(|arg| arg == "--quiet")
+        };
+        assert!(!has_quiet(&RustBuild::new()));
+        assert!(has_quiet(&RustBuild::new().progress(true)));
+
+        let mut config: RustBuildConfig = toml::from_str("").unwrap();
+        config.show_progress(true);
+        assert!(RustBuild::try_from(config).unwrap().progress);
+    }
+
+    #[test]
+    fn test_parse_artifact_package() {
+        let line = r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0","target":{"name":"serde","kind":["lib"]},"executable":null}"#;
+        let artifact = parse_artifact(line).unwrap();
+        assert_eq!(artifact.package_id, "serde 1.0.0");
+        assert_eq!(artifact.target.name, "serde");
+
+        let message = r#"{"reason":"compiler-message","package_id":"serde 1.0.0","target":{"name":"serde","kind":["lib"]}}"#;
+        assert!(parse_artifact(message).is_none());
+    }
+
+    #[test]
+    fn test_count_build_units() {
+        let resolve: Resolve = serde_json::from_str(
+            r#"{"nodes": [
+                {"id": "app", "deps": [
+                    {"pkg": "serde", "dep_kinds": [{"kind": null}]},
+                    {"pkg": "cc", "dep_kinds": [{"kind": "build"}]},
+                    {"pkg": "mockall", "dep_kinds": [{"kind": "dev"}]}
+                ]},
+                {"id": "serde", "deps": [
+                    {"pkg": "serde_derive", "dep_kinds": [{"kind": null}]}
+                ]},
+                {"id": "serde_derive", "deps": []},
+                {"id": "cc", "deps": []},
+                {"id": "mockall", "deps": [
+                    {"pkg": "predicates", "dep_kinds": [{"kind": null}]}
+                ]},
+                {"id": "predicates", "deps": []}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(count_build_units(&resolve.nodes, "app"), 4);
+        assert_eq!(count_build_units(&resolve.nodes, "serde"), 2);
+    }
+
+    #[test]
+    fn test_cross_builder() {
+        let config: RustBuildConfig = toml::from_str(r#"builder = "cross""#).unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.builder, Builder::Cross);
+
+        let cmd = build.cargo_build_command(Path::new("."));
+        assert_eq!(cmd.as_std().get_program(), OsStr::new("cross"));
+    }
+
+    #[test]
+    fn test_override_with() {
+        let mut config: RustBuildConfig = toml::from_str(
+            r#"
+            package_name = "api"
+            binary_name = "api"
+            target = ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]
+            "#,
+        )
+        .unwrap();
+
+        config.override_with(Some("worker".to_string()), None, Some("x86_64".to_string()));
+        assert_eq!(config.package_name.as_deref(), Some("worker"));
+        assert_eq!(config.binary_name.as_deref(), Some("api"));
+        assert_eq!(
+            config.target,
+            Some(TargetSpec::Single("x86_64".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_compress_binary_is_opt_in() {
+        let config: RustBuildConfig = toml::from_str("").unwrap();
+        assert!(!RustBuild::try_from(config).unwrap().compress);
+
+        let config: RustBuildConfig = toml::from_str("compress_binary = true").unwrap();
+        assert!(RustBuild::try_from(config).unwrap().compress);
+    }
+
+    #[test]
+    fn test_strip_defaults_to_profile() {
+        let config: RustBuildConfig = toml::from_str(r#"profile = "release""#).unwrap();
+        assert!(RustBuild::try_from(config).unwrap().strip);
+
+        let config: RustBuildConfig = toml::from_str(r#"profile = "debug""#).unwrap();
+        assert!(!RustBuild::try_from(config).unwrap().strip);
+
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            profile = "release"
+            strip = false
+            "#,
+        )
+        .unwrap();
+        assert!(!RustBuild::try_from(config).unwrap().strip);
+    }
+
+    #[test]
+    fn test_is_target_installed() {
+        let installed = "x86_64-unknown-linux-gnu\nx86_64-unknown-linux-musl\n";
+        assert!(is_target_installed(installed, "x86_64-unknown-linux-musl"));
+        assert!(!is_target_installed(
+            installed,
+            "aarch64-unknown-linux-musl"
+        ));
+    }
+
+    #[test]
+    fn test_target_dir_names() {
+        let targets = vec![
+            "x86_64-unknown-linux-musl".to_string(),
+            "aarch64-unknown-linux-musl".to_string(),
+        ];
+        assert_eq!(target_dir_names(&targets), vec!["x86_64", "aarch64"]);
+
+        let targets = vec![
+            "x86_64-unknown-linux-musl".to_string(),
+            "x86_64-unknown-linux-gnu".to_string(),
+        ];
+        assert_eq!(target_dir_names(&targets), targets);
+    }
+
+    fn parse_executable(line: &str) -> Option<(String, PathBuf)> {
+        parse_artifact(line)?.into_executable()
+    }
+
+    #[test]
+    fn test_parse_executable() {
+        let line = r#"{"reason":"compiler-artifact","target":{"name":"api","kind":["bin"]},"executable":"/target/release/api"}"#;
+        assert_eq!(
+            parse_executable(line),
+            Some(("api".to_string(), PathBuf::from("/target/release/api")))
+        );
+
+        let lib = r#"{"reason":"compiler-artifact","target":{"name":"shared","kind":["lib"]},"executable":null}"#;
+        assert_eq!(parse_executable(lib), None);
+        assert_eq!(
+            parse_executable(r#"{"reason":"build-finished","success":true}"#),
+            None
+        );
+        assert_eq!(parse_executable("not json"), None);
+
+        let example = r#"{"reason":"compiler-artifact","target":{"name":"api","kind":["example"]},"executable":"/target/release/examples/api"}"#;
+        assert_eq!(parse_executable(example), None);
+    }
+
+    #[test]
+    fn test_locate_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("custom")).unwrap();
+        let reported = dir.path().join("custom").join("api");
+        std::fs::write(&reported, "bin").unwrap();
+        let missing = dir.path().join("release").join("api");
+
+        let executables = HashMap::from([("api".to_string(), reported.clone())]);
+        assert_eq!(
+            locate_binary("api", missing.clone(), &executables, true).unwrap(),
+            reported
+        );
+
+        // Without verification a missing binary falls back to the only reported executable
+        let other = HashMap::from([("worker".to_string(), reported.clone())]);
+        assert!(locate_binary("api", missing.clone(), &other, true).is_err());
+        assert_eq!(
+            locate_binary("api", missing.clone(), &other, false).unwrap(),
+            reported
+        );
+        assert!(locate_binary("api", missing.clone(), &HashMap::new(), false).is_err());
+
+        // The expected path is the fallback when cargo reported nothing usable
+        std::fs::create_dir(dir.path().join("release")).unwrap();
+        std::fs::write(&missing, "bin").unwrap();
+        let container = HashMap::from([("api".to_string(), PathBuf::from("/target/api"))]);
+        assert_eq!(
+            locate_binary("api", missing.clone(), &container, true).unwrap(),
+            missing
+        );
+    }
+
+    #[test]
+    fn test_verify_build_config() {
+        let config: RustBuildConfig = toml::from_str("").unwrap();
+        assert!(RustBuild::try_from(config).unwrap().verify_build);
+
+        let mut config: RustBuildConfig = toml::from_str("").unwrap();
+        config.skip_verify_build();
+        assert!(!RustBuild::try_from(config).unwrap().verify_build);
+    }
+
+    #[test]
+    fn test_archives_in_place() {
+        let build = RustBuild::new();
+        assert!(build.archives_in_place());
+
+        let config: RustBuildConfig = toml::from_str("").unwrap();
+        let release = RustBuild::try_from(config).unwrap();
+        assert!(release.strip);
+        assert!(release.archives_in_place());
+
+        assert!(!build.clone().compress(true).archives_in_place());
+        assert!(
+            !build
+                .clone()
+                .output_template("{arch}/bootstrap")
+                .archives_in_place()
+        );
+        assert!(!build.binaries(vec!["api".to_string()]).archives_in_place());
+    }
+
+    #[test]
+    fn test_output_template() {
+        let build = RustBuild::new()
+            .target("aarch64-unknown-linux-musl".to_string())
+            .output_template("{arch}-{os}/{binary}-{profile}");
+
+        assert_eq!(
+            build.output_path(Path::new("/out"), "api").unwrap(),
+            Path::new("/out/aarch64-linux/api-release")
+        );
+        assert_eq!(
+            build
+                .output_template("{target}/bootstrap")
+                .output_path(Path::new("/out"), "api")
+                .unwrap(),
+            Path::new("/out/aarch64-unknown-linux-musl/bootstrap")
+        );
+    }
+
+    #[test]
+    fn test_validate_output_template() {
+        assert!(validate_output_template("bootstrap-{arch}", false).is_ok());
+        assert!(validate_output_template("{tripple}/bootstrap", false).is_err());
+        assert!(validate_output_template("{arch/bootstrap", false).is_err());
+        assert!(validate_output_template("arch}/bootstrap", false).is_err());
+        assert!(validate_output_template("../{arch}", false).is_err());
+        assert!(validate_output_template("/{arch}", false).is_err());
+        assert!(validate_output_template("{arch}/bootstrap", true).is_err());
+    }
+
+    #[test]
+    fn test_output_template_distinct_across_targets() {
+        let targets = vec![
+            "x86_64-unknown-linux-musl".to_string(),
+            "aarch64-unknown-linux-musl".to_string(),
+        ];
+
+        let build = RustBuild::new().output_template("bootstrap-{arch}");
+        assert!(check_distinct_outputs(&build, &targets).is_ok());
+
+        let build = RustBuild::new().output_template("{os}/bootstrap");
+        assert!(check_distinct_outputs(&build, &targets).is_err());
+    }
+
+    #[test]
+    fn test_cargo_build_args_default() {
+        let build = RustBuild::default();
+        assert_eq!(
+            build.cargo_build_args(),
+            vec![
+                "build",
+                "--release",
+                "--config",
+                "profile.release.strip=true",
+                "--target",
+                "x86_64-unknown-linux-musl"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_build_args_features() {
+        let build = RustBuild::new()
+            .profile(BuildProfile::Debug)
+            .features(vec!["foo".to_string(), "bar".to_string()])
+            .no_default_features(true);
+
+        assert_eq!(
+            build.cargo_build_args(),
+            vec![
+                "build",
+                "--config",
+                "profile.dev.strip=true",
+                "--target",
+                "x86_64-unknown-linux-musl",
+                "--features",
+                "foo,bar",
+                "--no-default-features"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_without_config() {
+        let build = RustBuild::new().strip(false);
+        assert!(!build.cargo_build_args().contains(&"--config".to_string()));
+    }
+
+    #[test]
+    fn test_named_profile() {
+        let config: RustBuildConfig = toml::from_str(r#"profile = "dist""#).unwrap();
+        let build = RustBuild::try_from(config).unwrap();
+        assert_eq!(build.profile, BuildProfile::Named("dist".to_string()));
+        assert!(build.strip);
+
+        assert_eq!(
+            build.cargo_build_args(),
+            vec![
+                "build",
+                "--profile",
+                "dist",
+                "--config",
+                "profile.dist.strip=true",
+                "--target",
+                "x86_64-unknown-linux-musl"
+            ]
+        );
+        assert_eq!(
+            build.get_binary_path(Path::new("/project/target"), "app"),
+            Path::new("/project/target/x86_64-unknown-linux-musl/dist/app")
+        );
+    }
+
+    #[test]
+    fn test_workspace_member_binary_path() {
+        let metadata: CargoMetadata = serde_json::from_str(
+            r#"{
+                "packages": [
+                    {
+                        "name": "shared",
+                        "manifest_path": "/workspace/shared/Cargo.toml",
+                        "targets": [{ "name": "shared", "kind": ["lib"] }]
+                    },
+                    {
+                        "name": "handler",
+                        "manifest_path": "/workspace/handler/Cargo.toml",
+                        "targets": [{ "name": "handler", "kind": ["bin"] }]
+                    }
+                ],
+                "target_directory": "/workspace/target",
+                "workspace_root": "/workspace"
+            }"#,
+        )
+        .unwrap();
+
+        let build = RustBuild::new();
+        let project_path = Path::new("/workspace/handler");
+        let package = build.find_package(&metadata, project_path).unwrap();
+        assert_eq!(package.name, "handler");
+
+        let binary = build.find_binary_target(package).unwrap();
+        let target_directory = build.target_directory(&metadata, project_path);
+        assert_eq!(
+            build.get_binary_path(&target_directory, &binary.name),
+            Path::new("/workspace/target/x86_64-unknown-linux-musl/release/handler")
+        );
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("api", "api"));
+        assert!(!matches_pattern("api", "api-worker"));
+        assert!(matches_pattern("api-*", "api-worker"));
+        assert!(matches_pattern("*-worker", "api-worker"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(!matches_pattern("job-*", "api-worker"));
+    }
+
+    #[test]
+    fn test_find_binary_targets_by_pattern() {
+        let package: Package = serde_json::from_str(
+            r#"{
+                "name": "services",
+                "manifest_path": "/workspace/services/Cargo.toml",
+                "targets": [
+                    { "name": "services", "kind": ["lib"] },
+                    { "name": "api-server", "kind": ["bin"] },
+                    { "name": "api-worker", "kind": ["bin"] },
+                    { "name": "migrate", "kind": ["bin"] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let build = RustBuild::new().binaries(vec!["api-*".to_string(), "api-server".to_string()]);
+        let names: Vec<&str> = build
+            .find_binary_targets(&package)
+            .unwrap()
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(names, ["api-server", "api-worker"]);
+
+        let build = RustBuild::new().binaries(vec!["cron-*".to_string()]);
+        assert!(build.find_binary_targets(&package).is_err());
+    }
+
+    #[test]
+    fn test_binaries_output_path() {
+        let build = RustBuild::new().binaries(vec!["*".to_string()]);
+        assert_eq!(
+            build.output_path(Path::new("/tmp/out"), "api").unwrap(),
+            Path::new("/tmp/out/bin/api")
+        );
+        assert_eq!(
+            RustBuild::new()
+                .output_path(Path::new("/tmp/out"), "api")
+                .unwrap(),
+            Path::new("/tmp/out/bootstrap")
+        );
+    }
+
+    #[test]
+    fn test_binaries_conflicts_with_binary_name() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            binary_name = "api"
+            binaries = ["api", "worker"]
+            "#,
+        )
+        .unwrap();
+        assert!(RustBuild::try_from(config).is_err());
+    }
+
+    fn workspace_metadata(packages: &str) -> CargoMetadata {
+        serde_json::from_str(&format!(
+            r#"{{ "packages": [{}], "workspace_root": "/workspace" }}"#,
+            packages
+        ))
+        .unwrap()
+    }
+
+    const SHARED_LIB: &str = r#"{
+        "name": "shared",
+        "manifest_path": "/workspace/shared/Cargo.toml",
+        "targets": [{ "name": "shared", "kind": ["lib"] }]
+    }"#;
+
+    const HANDLER_BIN: &str = r#"{
+        "name": "handler",
+        "manifest_path": "/workspace/handler/Cargo.toml",
+        "targets": [{ "name": "handler", "kind": ["bin"] }]
+    }"#;
+
+    const WORKER_BIN: &str = r#"{
+        "name": "worker",
+        "manifest_path": "/workspace/worker/Cargo.toml",
+        "targets": [{ "name": "worker", "kind": ["bin"] }]
+    }"#;
+
+    #[test]
+    fn test_lib_package_falls_back_to_unique_binary() {
+        let metadata = workspace_metadata(&format!("{},{}", SHARED_LIB, HANDLER_BIN));
+
+        let package = RustBuild::new()
+            .find_package(&metadata, Path::new("/workspace"))
+            .unwrap();
+        assert_eq!(package.name, "handler");
+    }
+
+    #[test]
+    fn test_lib_package_lists_binary_candidates() {
+        let metadata =
+            workspace_metadata(&format!("{},{},{}", SHARED_LIB, HANDLER_BIN, WORKER_BIN));
+
+        let err = RustBuild::new()
+            .find_package(&metadata, Path::new("/workspace"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("handler (handler)"), "{}", err);
+        assert!(err.contains("worker (worker)"), "{}", err);
+
+        let package = RustBuild::new()
+            .binary_name("worker")
+            .find_package(&metadata, Path::new("/workspace"))
+            .unwrap();
+        assert_eq!(package.name, "worker");
+    }
+
+    #[test]
+    fn test_workspace_without_binaries() {
+        let metadata = workspace_metadata(SHARED_LIB);
+
+        assert!(
+            RustBuild::new()
+                .find_package(&metadata, Path::new("/workspace"))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_uses_loaded_metadata() {
+        let mut config: RustBuildConfig = toml::from_str("").unwrap();
+        config.metadata = Some(Arc::new(workspace_metadata(HANDLER_BIN)));
+
+        // No Cargo.toml exists here, so running cargo metadata would fail
+        let resolved = config
+            .resolve(Path::new("/nonexistent/project"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.package, "handler");
+        assert_eq!(resolved.binary, "handler");
+    }
+
+    #[test]
+    fn test_target_directory_resolution() {
+        let project_path = Path::new("/project");
+        let metadata: CargoMetadata =
+            serde_json::from_str(r#"{ "packages": [], "target_directory": "/cache/target" }"#)
+                .unwrap();
+        let without_dir: CargoMetadata = serde_json::from_str(r#"{ "packages": [] }"#).unwrap();
+
+        let build = RustBuild::new();
+        assert_eq!(
+            build.target_directory(&metadata, project_path),
+            Path::new("/cache/target")
+        );
+        assert_eq!(
+            build.target_directory(&without_dir, project_path),
+            Path::new("/project/target")
+        );
+
+        let build = RustBuild::new().extra_args(vec!["--target-dir".into(), "out".into()]);
+        assert_eq!(
+            build.target_directory(&metadata, project_path),
+            Path::new("/project/out")
+        );
+
+        let build = RustBuild::new().extra_args(vec!["--target-dir=/tmp/out".into()]);
+        assert_eq!(
+            build.target_directory(&metadata, project_path),
+            Path::new("/tmp/out")
+        );
+    }
+
+    #[test]
+    fn test_builtin_profile_binary_path() {
+        let build = RustBuild::new().profile(BuildProfile::Debug);
+        assert_eq!(
+            build.get_binary_path(Path::new("/project/target"), "app"),
+            Path::new("/project/target/x86_64-unknown-linux-musl/debug/app")
+        );
+
+        assert_eq!(BuildProfile::from_name("dev"), BuildProfile::Debug);
+        assert_eq!(BuildProfile::from_name("Release"), BuildProfile::Release);
+    }
+
+    #[test]
+    fn test_cargo_build_args_all_features() {
+        let build = RustBuild::new().all_features(true);
+        assert!(
+            build
+                .cargo_build_args()
+                .contains(&"--all-features".to_string())
+        );
+        assert!(!build.cargo_build_args().contains(&"--features".to_string()));
+    }
+
+    #[test]
+    fn test_all_features_conflicts_with_features() {
+        let config: RustBuildConfig = toml::from_str(
+            r#"
+            features = ["foo"]
+            all_features = true
+            "#,
+        )
+        .unwrap();
+
+        assert!(RustBuild::try_from(config).is_err());
     }
 }
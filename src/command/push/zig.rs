@@ -0,0 +1,229 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tokio::process::Command;
+use tonic::async_trait;
+use tracing::info;
+
+use super::{BuildService, make_executable, require_tool, run_with_timeout};
+
+/// Optimize mode passed to `zig build` as `-Doptimize=<mode>`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub enum OptimizeMode {
+    Debug,
+    #[default]
+    ReleaseSafe,
+    ReleaseFast,
+    ReleaseSmall,
+}
+
+impl OptimizeMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            OptimizeMode::Debug => "Debug",
+            OptimizeMode::ReleaseSafe => "ReleaseSafe",
+            OptimizeMode::ReleaseFast => "ReleaseFast",
+            OptimizeMode::ReleaseSmall => "ReleaseSmall",
+        }
+    }
+}
+
+/// Build a Zig project with `zig build` and copy the installed executable
+#[derive(Debug, Deserialize)]
+pub struct ZigBuildConfig {
+    /// Target passed as `-Dtarget=<target>` (e.g. "x86_64-linux-musl"), the host when unset
+    #[serde(default)]
+    target: Option<String>,
+
+    /// Optimize mode (default: ReleaseSafe)
+    #[serde(default)]
+    optimize: OptimizeMode,
+
+    /// Name of the executable installed to `<prefix>/bin`
+    binary: String,
+
+    /// Install prefix, relative to the project path
+    #[serde(default = "default_prefix")]
+    prefix: PathBuf,
+
+    /// Extra arguments appended to the `zig build` invocation
+    #[serde(default)]
+    extra_args: Vec<String>,
+
+    /// Name the executable is copied to in the artifact
+    #[serde(default = "super::default_entrypoint")]
+    entrypoint: String,
+
+    /// Timeout for `zig build` in seconds (default: 900 seconds / 15 minutes)
+    #[serde(default = "default_timeout")]
+    timeout_seconds: u64,
+}
+
+fn default_prefix() -> PathBuf {
+    PathBuf::from("zig-out")
+}
+
+fn default_timeout() -> u64 {
+    900 // 15 minutes
+}
+
+impl ZigBuildConfig {
+    /// Validate the configuration and that the project has a `build.zig`
+    pub(super) fn validate(&self, project_path: &Path) -> anyhow::Result<()> {
+        if self.binary.trim().is_empty() {
+            bail!("Zig build binary cannot be empty");
+        }
+
+        if self.binary.contains(['/', '\\']) {
+            bail!(
+                "Zig build binary must be a file name in {:?}, got '{}'",
+                self.prefix.join("bin"),
+                self.binary
+            );
+        }
+
+        if self.timeout_seconds == 0 {
+            bail!("Timeout must be greater than 0");
+        }
+
+        let build_zig = project_path.join("build.zig");
+        if !build_zig.is_file() {
+            bail!("No build.zig found at {:?}", build_zig);
+        }
+
+        Ok(())
+    }
+
+    /// Arguments for `zig build`
+    fn build_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "build".to_string(),
+            format!("-Doptimize={}", self.optimize.as_str()),
+        ];
+
+        if let Some(ref target) = self.target {
+            args.push(format!("-Dtarget={}", target));
+        }
+
+        args.push("--prefix".to_string());
+        args.push(self.prefix.display().to_string());
+
+        // User supplied arguments go last so they can extend the generated flags
+        args.extend(self.extra_args.iter().cloned());
+
+        args
+    }
+
+    /// Path `zig build` installs the executable to
+    fn binary_path(&self, project_path: &Path) -> PathBuf {
+        project_path
+            .join(&self.prefix)
+            .join("bin")
+            .join(&self.binary)
+    }
+
+    /// Run `zig build` in the project directory
+    async fn run_zig_build(&self, project_path: &Path) -> anyhow::Result<()> {
+        let mut cmd = Command::new("zig");
+        cmd.args(self.build_args()).current_dir(project_path);
+        run_with_timeout(cmd, "zig build", self.timeout_seconds).await
+    }
+}
+
+#[async_trait]
+impl BuildService for ZigBuildConfig {
+    async fn build(&self, project_path: PathBuf, temp_path: PathBuf) -> anyhow::Result<()> {
+        self.validate(&project_path)
+            .context("Invalid zig build configuration")?;
+        require_tool("zig", &["version"]).await?;
+
+        info!(
+            "Building with zig ({}, target {})",
+            self.optimize.as_str(),
+            self.target.as_deref().unwrap_or("host")
+        );
+        self.run_zig_build(&project_path).await?;
+
+        let binary_path = self.binary_path(&project_path);
+        if !binary_path.is_file() {
+            bail!(
+                "zig build did not install '{}' at {:?}, check the binary setting",
+                self.binary,
+                binary_path
+            );
+        }
+
+        let output_path = temp_path.join(&self.entrypoint);
+        info!("Copying {:?} as '{}'", binary_path, self.entrypoint);
+        tokio::fs::copy(&binary_path, &output_path)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to {:?}", binary_path, output_path))?;
+
+        make_executable(&output_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: &str) -> ZigBuildConfig {
+        toml::from_str(&format!("binary = \"handler\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = config("");
+        assert_eq!(config.target, None);
+        assert_eq!(config.optimize, OptimizeMode::ReleaseSafe);
+        assert_eq!(config.prefix, PathBuf::from("zig-out"));
+        assert!(config.extra_args.is_empty());
+        assert_eq!(config.entrypoint, "bootstrap");
+        assert_eq!(config.timeout_seconds, 900);
+    }
+
+    #[test]
+    fn test_build_args() {
+        let config = config(
+            "target = \"x86_64-linux-musl\"\n\
+             optimize = \"ReleaseSmall\"\n\
+             extra_args = [\"--summary\", \"all\"]",
+        );
+
+        assert_eq!(
+            config.build_args(),
+            vec![
+                "build",
+                "-Doptimize=ReleaseSmall",
+                "-Dtarget=x86_64-linux-musl",
+                "--prefix",
+                "zig-out",
+                "--summary",
+                "all",
+            ]
+        );
+        assert_eq!(
+            config.binary_path(Path::new("/project")),
+            Path::new("/project/zig-out/bin/handler")
+        );
+    }
+
+    #[test]
+    fn test_validate_requires_build_zig() {
+        let project_dir = tempfile::tempdir().unwrap();
+        assert!(config("").validate(project_dir.path()).is_err());
+
+        std::fs::write(project_dir.path().join("build.zig"), "").unwrap();
+        assert!(config("").validate(project_dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_binary_name() {
+        let project_dir = tempfile::tempdir().unwrap();
+        std::fs::write(project_dir.path().join("build.zig"), "").unwrap();
+
+        let config: ZigBuildConfig = toml::from_str("binary = \"bin/handler\"").unwrap();
+        assert!(config.validate(project_dir.path()).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use tonic::{Status, Streaming};
+
+use crate::command::transport::{AuthChannel, ConnectOptions, RawClient};
+
+/// Registry calls that are not part of the generated `RegistryServiceClient`
+pub struct RegistryClient {
+    inner: RawClient,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RegistryPullRequest {
+    #[prost(string, tag = "1")]
+    pub digest: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RegistryPullResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+}
+
+impl RegistryClient {
+    pub fn new(channel: AuthChannel, options: &ConnectOptions) -> Self {
+        Self {
+            inner: RawClient::new(channel, options),
+        }
+    }
+
+    /// Stream the artifact stored under `digest` in chunks
+    pub async fn pull(
+        &mut self,
+        digest: String,
+    ) -> Result<Streaming<RegistryPullResponse>, Status> {
+        self.inner
+            .server_streaming(
+                "/noctiforge.registry.RegistryService/Pull",
+                RegistryPullRequest { digest },
+            )
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use tracing::{debug, info};
+
+use crate::command::health::{HealthClient, ServingStatus};
+use crate::command::transport::{self, ConnectOptions};
+use crate::command::trigger::{resolve_worker_url, validate_worker_url};
+
+/// Check that the worker is reachable and reports itself as serving, printing the
+/// result unless quiet
+pub async fn run(worker_url: Option<String>, service: String, quiet: bool) -> Result<()> {
+    let worker_url = resolve_worker_url(worker_url);
+    validate_worker_url(&worker_url)?;
+
+    info!("Connecting to WorkerService at {}...", worker_url);
+    let connect_options = ConnectOptions::from_env();
+    let request_timeout = connect_options.timeouts.request_timeout();
+    let started = Instant::now();
+    let channel = transport::connect(&worker_url, &connect_options).await?;
+    let connect_latency = started.elapsed();
+    debug!(?connect_latency, "Connected to WorkerService");
+
+    let mut client = HealthClient::new(channel, &connect_options);
+    let started = Instant::now();
+    let status = client
+        .check(service)
+        .await
+        .map_err(|e| transport::call_error(e, "check worker health", request_timeout))?;
+    let latency = started.elapsed();
+
+    if !quiet {
+        println!(
+            "{}: {} (connect {}, check {})",
+            worker_url,
+            describe(status),
+            format_latency(connect_latency),
+            format_latency(latency)
+        );
+    }
+
+    if status != ServingStatus::Serving {
+        bail!(
+            "Worker at {} is not serving ({})",
+            worker_url,
+            describe(status)
+        );
+    }
+
+    Ok(())
+}
+
+/// Human readable name of a serving status
+pub(super) fn describe(status: ServingStatus) -> &'static str {
+    match status {
+        ServingStatus::Serving => "serving",
+        ServingStatus::NotServing => "not serving",
+        ServingStatus::ServiceUnknown => "unknown service",
+        ServingStatus::Unknown => "unknown",
+    }
+}
+
+pub(super) fn format_latency(latency: Duration) -> String {
+    format!("{:.1}ms", latency.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_latency() {
+        assert_eq!(format_latency(Duration::from_micros(12_345)), "12.3ms");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_worker_fails() {
+        assert!(
+            run(Some("http://127.0.0.1:1".to_string()), String::new(), false)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,867 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use tonic::codec::CompressionEncoding;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Uri};
+use tonic::{Code, IntoRequest, Request, Status, Streaming};
+use tracing::debug;
+
+/// Channel type returned by [`connect`], with authentication attached
+pub type AuthChannel = InterceptedService<Channel, AuthInterceptor>;
+
+const DEFAULT_CONNECT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 200;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_PUSH_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_MAX_MESSAGE_BYTES: usize = 512 * 1024 * 1024;
+
+/// Exponential backoff policy for establishing connections
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Number of retries after the first failed attempt
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for every following retry
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_CONNECT_RETRIES,
+            base_delay: Duration::from_millis(DEFAULT_RETRY_BASE_DELAY_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read the policy from `NOCTI_CONNECT_RETRIES` and `NOCTI_CONNECT_RETRY_DELAY_MS`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let retries = std::env::var("NOCTI_CONNECT_RETRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default.retries);
+        let base_delay = std::env::var("NOCTI_CONNECT_RETRY_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(default.base_delay);
+
+        Self {
+            retries,
+            base_delay,
+        }
+    }
+
+    /// Delay before the given retry (zero based)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// TLS settings for gRPC client connections
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// Path to a PEM encoded CA certificate used to verify the server
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Domain name used for SNI and certificate verification
+    #[serde(default)]
+    pub domain: Option<String>,
+
+    /// Path to a PEM encoded client certificate for mutual TLS
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+
+    /// Path to the PEM encoded private key for `client_cert`
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Build a TLS config purely from the `NOCTI_TLS_*` variables
+    pub fn from_env() -> Self {
+        Self::default().with_env_fallback()
+    }
+
+    /// Fill in any unset fields from `NOCTI_TLS_CA`, `NOCTI_TLS_DOMAIN`,
+    /// `NOCTI_TLS_CLIENT_CERT` and `NOCTI_TLS_CLIENT_KEY`
+    pub fn with_env_fallback(mut self) -> Self {
+        if self.ca_cert.is_none() {
+            self.ca_cert = std::env::var("NOCTI_TLS_CA").ok().map(PathBuf::from);
+        }
+        if self.domain.is_none() {
+            self.domain = std::env::var("NOCTI_TLS_DOMAIN").ok();
+        }
+        if self.client_cert.is_none() {
+            self.client_cert = std::env::var("NOCTI_TLS_CLIENT_CERT")
+                .ok()
+                .map(PathBuf::from);
+        }
+        if self.client_key.is_none() {
+            self.client_key = std::env::var("NOCTI_TLS_CLIENT_KEY")
+                .ok()
+                .map(PathBuf::from);
+        }
+        self
+    }
+
+    /// Load the client certificate and key for mutual TLS, if configured
+    async fn client_identity(&self) -> Result<Option<Identity>> {
+        let (cert, key) = match (&self.client_cert, &self.client_key) {
+            (None, None) => return Ok(None),
+            (Some(cert), Some(key)) => (cert, key),
+            (Some(_), None) => bail!("A TLS client certificate was given without a client key"),
+            (None, Some(_)) => bail!("A TLS client key was given without a client certificate"),
+        };
+
+        let cert = tokio::fs::read(cert)
+            .await
+            .with_context(|| format!("Failed to read TLS client certificate: {:?}", cert))?;
+        let key = tokio::fs::read(key)
+            .await
+            .with_context(|| format!("Failed to read TLS client key: {:?}", key))?;
+
+        Ok(Some(Identity::from_pem(cert, key)))
+    }
+
+    async fn client_tls_config(&self, identity: Option<Identity>) -> Result<ClientTlsConfig> {
+        let mut tls = ClientTlsConfig::new().with_native_roots();
+
+        if let Some(ref ca_cert) = self.ca_cert {
+            let pem = tokio::fs::read(ca_cert)
+                .await
+                .with_context(|| format!("Failed to read CA certificate: {:?}", ca_cert))?;
+            tls = tls.ca_certificate(Certificate::from_pem(pem));
+        }
+
+        if let Some(ref domain) = self.domain {
+            tls = tls.domain_name(domain.clone());
+        }
+
+        if let Some(identity) = identity {
+            tls = tls.identity(identity);
+        }
+
+        Ok(tls)
+    }
+}
+
+/// Connect and request timeouts for gRPC clients, in seconds
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct TimeoutConfig {
+    /// Time allowed to establish a connection (default: 10)
+    #[serde(default)]
+    pub connect_seconds: Option<u64>,
+
+    /// Deadline for each unary request (default: 60, 0 disables the deadline)
+    #[serde(default)]
+    pub request_seconds: Option<u64>,
+
+    /// Deadline for the artifact upload stream (default: 600, 0 disables the deadline)
+    #[serde(default)]
+    pub push_seconds: Option<u64>,
+}
+
+impl TimeoutConfig {
+    /// Build the timeouts purely from the environment
+    pub fn from_env() -> Self {
+        Self::default().with_env_fallback()
+    }
+
+    /// Fill in any unset fields from `NOCTI_CONNECT_TIMEOUT`, `NOCTI_REQUEST_TIMEOUT`
+    /// and `NOCTI_PUSH_TIMEOUT`
+    pub fn with_env_fallback(mut self) -> Self {
+        let from_env = |name: &str| std::env::var(name).ok().and_then(|v| v.parse().ok());
+
+        self.connect_seconds = self
+            .connect_seconds
+            .or_else(|| from_env("NOCTI_CONNECT_TIMEOUT"));
+        self.request_seconds = self
+            .request_seconds
+            .or_else(|| from_env("NOCTI_REQUEST_TIMEOUT"));
+        self.push_seconds = self.push_seconds.or_else(|| from_env("NOCTI_PUSH_TIMEOUT"));
+        self
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_seconds.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS))
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        deadline(self.request_seconds.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS))
+    }
+
+    pub fn push_timeout(&self) -> Option<Duration> {
+        deadline(self.push_seconds.unwrap_or(DEFAULT_PUSH_TIMEOUT_SECS))
+    }
+
+    /// Use the push deadline as the request timeout, for channels carrying the upload stream
+    pub fn for_push(mut self) -> Self {
+        self.request_seconds = Some(self.push_seconds.unwrap_or(DEFAULT_PUSH_TIMEOUT_SECS));
+        self
+    }
+}
+
+/// Convert a deadline in seconds, where 0 means no deadline
+fn deadline(seconds: u64) -> Option<Duration> {
+    (seconds > 0).then_some(Duration::from_secs(seconds))
+}
+
+/// Maximum gRPC message sizes, applied to the generated clients and to every `RawClient`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageLimits {
+    /// Largest message the client will send
+    pub max_encoding: usize,
+
+    /// Largest message the client will accept
+    pub max_decoding: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_encoding: DEFAULT_MAX_MESSAGE_BYTES,
+            max_decoding: DEFAULT_MAX_MESSAGE_BYTES,
+        }
+    }
+}
+
+impl MessageLimits {
+    /// Read the limits from `NOCTI_MAX_SEND_MESSAGE_BYTES` and `NOCTI_MAX_RECV_MESSAGE_BYTES`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let from_env = |name: &str| std::env::var(name).ok().and_then(|v| v.parse().ok());
+
+        Self {
+            max_encoding: from_env("NOCTI_MAX_SEND_MESSAGE_BYTES").unwrap_or(default.max_encoding),
+            max_decoding: from_env("NOCTI_MAX_RECV_MESSAGE_BYTES").unwrap_or(default.max_decoding),
+        }
+    }
+}
+
+/// Compression of the gRPC messages themselves, independent of artifact compression
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrpcCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+impl GrpcCompression {
+    /// Read the setting from `NOCTI_GRPC_COMPRESSION` (`none` or `gzip`)
+    pub fn from_env() -> Self {
+        match std::env::var("NOCTI_GRPC_COMPRESSION") {
+            Ok(value) if value.eq_ignore_ascii_case("gzip") => Self::Gzip,
+            _ => Self::None,
+        }
+    }
+
+    /// Encoding to send and accept, if compression is enabled
+    pub fn encoding(self) -> Option<CompressionEncoding> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some(CompressionEncoding::Gzip),
+        }
+    }
+}
+
+/// Bearer token sent as `authorization` metadata, redacted when debug printed
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    /// Read the token from `NOCTI_AUTH_TOKEN`
+    pub fn from_env() -> Option<Self> {
+        std::env::var("NOCTI_AUTH_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+            .map(Self)
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthToken(<redacted>)")
+    }
+}
+
+/// Credential sent as `authorization` metadata
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum Credential {
+    Bearer { token: AuthToken },
+    Basic { username: String, password: String },
+}
+
+impl Credential {
+    /// Value of the `authorization` header
+    fn header(&self) -> String {
+        match self {
+            Credential::Bearer { token } => format!("Bearer {}", token.0),
+            Credential::Basic { username, password } => {
+                format!(
+                    "Basic {}",
+                    BASE64.encode(format!("{}:{}", username, password))
+                )
+            }
+        }
+    }
+}
+
+/// Credentials keyed by `host` or `host:port`, read from the credentials file
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+struct CredentialsFile(HashMap<String, Credential>);
+
+impl CredentialsFile {
+    /// Path from `NOCTI_CREDENTIALS_FILE`, else `~/.noctiforge/credentials.toml`
+    fn path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("NOCTI_CREDENTIALS_FILE") {
+            return Some(PathBuf::from(path));
+        }
+
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(
+            PathBuf::from(home)
+                .join(".noctiforge")
+                .join("credentials.toml"),
+        )
+    }
+
+    /// Read the file at `path`, treating a missing file as empty
+    async fn load(path: &Path) -> Result<Self> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("Failed to parse credentials file {:?}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No credentials file at {:?}", path);
+                Ok(Self::default())
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read credentials file {:?}", path)),
+        }
+    }
+
+    /// Credential for the host of `uri`, preferring a `host:port` entry
+    fn lookup(&self, uri: &Uri) -> Option<&Credential> {
+        let host = uri.host()?;
+        uri.port_u16()
+            .and_then(|port| self.0.get(&format!("{}:{}", host, port)))
+            .or_else(|| self.0.get(host))
+    }
+}
+
+/// Credential for `uri`: the explicit token, else the credentials file entry for its host,
+/// else `NOCTI_AUTH_TOKEN`
+async fn resolve_credential(uri: &Uri, explicit: Option<&AuthToken>) -> Result<Option<Credential>> {
+    if let Some(token) = explicit {
+        return Ok(Some(Credential::Bearer {
+            token: token.clone(),
+        }));
+    }
+
+    if let Some(path) = CredentialsFile::path() {
+        let file = CredentialsFile::load(&path).await?;
+        if let Some(credential) = file.lookup(uri) {
+            debug!("Using credentials from {:?} for {}", path, uri);
+            return Ok(Some(credential.clone()));
+        }
+    }
+
+    Ok(AuthToken::from_env().map(|token| Credential::Bearer { token }))
+}
+
+/// Interceptor adding the credential to every outbound request
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    header: Option<MetadataValue<Ascii>>,
+}
+
+impl AuthInterceptor {
+    fn new(credential: Option<&Credential>) -> Result<Self> {
+        let header = credential
+            .map(|c| c.header().parse())
+            .transpose()
+            .context("Credential contains characters that are not valid in a header")?;
+
+        Ok(Self { header })
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(ref header) = self.header {
+            request
+                .metadata_mut()
+                .insert("authorization", header.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Settings shared by every gRPC client connection
+#[derive(Debug, Default, Clone)]
+pub struct ConnectOptions {
+    pub tls: TlsConfig,
+    /// Token used for every host, before the credentials file and `NOCTI_AUTH_TOKEN`
+    pub auth_token: Option<AuthToken>,
+    pub timeouts: TimeoutConfig,
+    pub limits: MessageLimits,
+    pub compression: GrpcCompression,
+}
+
+impl ConnectOptions {
+    /// Build the options purely from the environment, credentials are resolved per host
+    pub fn from_env() -> Self {
+        Self {
+            tls: TlsConfig::from_env(),
+            auth_token: None,
+            timeouts: TimeoutConfig::from_env(),
+            limits: MessageLimits::from_env(),
+            compression: GrpcCompression::from_env(),
+        }
+    }
+}
+
+/// gRPC client for calls made without a generated client, such as newer methods or the
+/// health service, with the connection's compression and message limits applied
+pub struct RawClient {
+    inner: tonic::client::Grpc<AuthChannel>,
+}
+
+impl RawClient {
+    pub fn new(channel: AuthChannel, options: &ConnectOptions) -> Self {
+        let limits = options.limits;
+        let mut inner = tonic::client::Grpc::new(channel)
+            .max_encoding_message_size(limits.max_encoding)
+            .max_decoding_message_size(limits.max_decoding);
+        if let Some(encoding) = options.compression.encoding() {
+            inner = inner.send_compressed(encoding).accept_compressed(encoding);
+        }
+
+        Self { inner }
+    }
+
+    /// Wait for the channel to accept a request
+    async fn ready(&mut self) -> Result<(), Status> {
+        self.inner
+            .ready()
+            .await
+            .map_err(|e| Status::unknown(format!("Service was not ready: {}", e)))
+    }
+
+    /// Issue a unary call to `method`, a full path like `/package.Service/Method`
+    pub async fn unary<Req, Resp>(
+        &mut self,
+        method: &'static str,
+        request: impl IntoRequest<Req>,
+    ) -> Result<Resp, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        self.ready().await?;
+
+        let codec = tonic_prost::ProstCodec::default();
+        let path = PathAndQuery::from_static(method);
+        let response = self
+            .inner
+            .unary(request.into_request(), path, codec)
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Open a server-streaming call to `method`, a full path like `/package.Service/Method`
+    pub async fn server_streaming<Req, Resp>(
+        &mut self,
+        method: &'static str,
+        request: impl IntoRequest<Req>,
+    ) -> Result<Streaming<Resp>, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        self.ready().await?;
+
+        let codec = tonic_prost::ProstCodec::default();
+        let path = PathAndQuery::from_static(method);
+        let response = self
+            .inner
+            .server_streaming(request.into_request(), path, codec)
+            .await?;
+
+        Ok(response.into_inner())
+    }
+}
+
+/// Check `url` is an `http` or `https` URL with a host, naming `service` in the error
+pub fn validate_url(url: &str, service: &str) -> Result<()> {
+    let uri = url
+        .parse::<Uri>()
+        .with_context(|| format!("Invalid {} URL: '{}'", service, url))?;
+
+    match uri.scheme_str() {
+        Some("http" | "https") if uri.host().is_some() => Ok(()),
+        Some("http" | "https") => bail!("Invalid {} URL: '{}' has no host", service, url),
+        Some(scheme) => bail!(
+            "Unsupported scheme '{}' in {} URL '{}', use http:// or https://",
+            scheme,
+            service,
+            url
+        ),
+        None => bail!(
+            "Missing scheme in {} URL '{}', did you mean 'http://{}'?",
+            service,
+            url,
+            url
+        ),
+    }
+}
+
+/// Create a channel to the given URL, enabling TLS when the scheme is `https`
+pub async fn connect(url: &str, options: &ConnectOptions) -> Result<AuthChannel> {
+    let tls = &options.tls;
+    let connect_timeout = options.timeouts.connect_timeout();
+    let mut endpoint = Endpoint::from_shared(url.to_string())
+        .with_context(|| format!("Invalid service URL: '{}'", url))?
+        .connect_timeout(connect_timeout);
+
+    if let Some(timeout) = options.timeouts.request_timeout() {
+        endpoint = endpoint.timeout(timeout);
+    }
+
+    // Load the client identity and credential up front so bad files fail before connecting
+    let identity = tls.client_identity().await?;
+    let credential = resolve_credential(endpoint.uri(), options.auth_token.as_ref()).await?;
+
+    if endpoint.uri().scheme_str() == Some("https") {
+        debug!("Enabling TLS for {}", url);
+        if identity.is_some() {
+            debug!("Using client certificate for mutual TLS with {}", url);
+        }
+        endpoint = endpoint
+            .tls_config(tls.client_tls_config(identity).await?)
+            .with_context(|| format!("Failed to configure TLS for {}", url))?;
+    } else if identity.is_some() {
+        debug!("Ignoring TLS client certificate for non-https URL {}", url);
+    }
+
+    let channel = match connect_with_retry(&endpoint, RetryPolicy::from_env()).await {
+        Ok(channel) => channel,
+        Err(e) if is_timeout(&e) => {
+            return Err(e).with_context(|| {
+                format!(
+                    "Timed out connecting to {} after {}s",
+                    url,
+                    connect_timeout.as_secs()
+                )
+            });
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to connect to {}", url)),
+    };
+
+    if credential.is_some() {
+        debug!("Attaching credentials to requests for {}", url);
+    }
+    let interceptor = AuthInterceptor::new(credential.as_ref())?;
+
+    Ok(InterceptedService::new(channel, interceptor))
+}
+
+/// Whether a status reports an expired client or server deadline
+pub fn is_timeout_status(status: &Status) -> bool {
+    match status.code() {
+        Code::DeadlineExceeded => true,
+        Code::Cancelled => status.message().to_lowercase().contains("timeout"),
+        _ => false,
+    }
+}
+
+/// Whether a failed call may succeed when repeated, e.g. after a dropped connection
+pub fn is_retryable_status(status: &Status) -> bool {
+    matches!(status.code(), Code::Unavailable | Code::Aborted)
+}
+
+/// Whether any error in the chain was caused by a timeout
+fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(e) = source {
+        let message = e.to_string().to_lowercase();
+        if e.downcast_ref::<std::io::Error>()
+            .is_some_and(|io| io.kind() == std::io::ErrorKind::TimedOut)
+            || e.downcast_ref::<Status>().is_some_and(is_timeout_status)
+            || message.contains("timed out")
+            || message.contains("timeout expired")
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Turn a failed call into an error, reporting timeouts separately from other failures
+pub fn call_error(status: Status, action: &str, timeout: Option<Duration>) -> anyhow::Error {
+    let message = match timeout {
+        _ if !is_timeout_status(&status) => format!("Failed to {}", action),
+        Some(timeout) => format!(
+            "Timed out trying to {} after {}s",
+            action,
+            timeout.as_secs()
+        ),
+        None => format!("Timed out trying to {}", action),
+    };
+
+    anyhow::Error::new(status).context(message)
+}
+
+/// Connect to the endpoint, retrying transport errors with exponential backoff
+async fn connect_with_retry(
+    endpoint: &Endpoint,
+    policy: RetryPolicy,
+) -> Result<Channel, tonic::transport::Error> {
+    let mut retry = 0;
+    loop {
+        match endpoint.connect().await {
+            Ok(channel) => return Ok(channel),
+            Err(e) if retry < policy.retries => {
+                let delay = policy.delay(retry);
+                retry += 1;
+                debug!(
+                    "Connection to {} failed ({}), retry {}/{} in {:?}",
+                    endpoint.uri(),
+                    e,
+                    retry,
+                    policy.retries,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let policy = RetryPolicy {
+            retries: 3,
+            base_delay: Duration::from_millis(100),
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_connect_gives_up_after_retries() {
+        let endpoint = Endpoint::from_static("http://127.0.0.1:1");
+        let policy = RetryPolicy {
+            retries: 2,
+            base_delay: Duration::from_millis(1),
+        };
+
+        assert!(connect_with_retry(&endpoint, policy).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_identity_requires_cert_and_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("client.pem");
+        std::fs::write(&cert, "cert").unwrap();
+
+        let tls = TlsConfig {
+            client_cert: Some(cert.clone()),
+            ..Default::default()
+        };
+        assert!(tls.client_identity().await.is_err());
+
+        let tls = TlsConfig {
+            client_cert: Some(cert),
+            client_key: Some(dir.path().join("missing.key")),
+            ..Default::default()
+        };
+        let err = tls.client_identity().await.unwrap_err();
+        assert!(err.to_string().contains("client key"));
+
+        assert!(
+            TlsConfig::default()
+                .client_identity()
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("http://localhost:50001", "registry").is_ok());
+        assert!(validate_url("https://registry.example.com", "registry").is_ok());
+
+        let err = validate_url("localhost:50001", "registry").unwrap_err();
+        assert!(err.to_string().contains("http://localhost:50001"));
+        assert!(validate_url("ftp://localhost:50001", "registry").is_err());
+        assert!(validate_url("not a url", "registry").is_err());
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(is_retryable_status(&Status::unavailable("reset")));
+        assert!(is_retryable_status(&Status::aborted("stream reset")));
+        assert!(!is_retryable_status(&Status::invalid_argument("bad")));
+        assert!(!is_retryable_status(&Status::deadline_exceeded("too slow")));
+    }
+
+    #[test]
+    fn test_grpc_compression_encoding() {
+        #[derive(Deserialize)]
+        struct Config {
+            grpc_compression: GrpcCompression,
+        }
+
+        let config: Config = toml::from_str(r#"grpc_compression = "gzip""#).unwrap();
+        assert_eq!(config.grpc_compression, GrpcCompression::Gzip);
+        assert_eq!(
+            config.grpc_compression.encoding(),
+            Some(CompressionEncoding::Gzip)
+        );
+        assert_eq!(GrpcCompression::default().encoding(), None);
+    }
+
+    #[test]
+    fn test_timeout_defaults() {
+        let timeouts = TimeoutConfig::default();
+        assert_eq!(timeouts.connect_timeout(), Duration::from_secs(10));
+        assert_eq!(timeouts.request_timeout(), Some(Duration::from_secs(60)));
+
+        let timeouts = TimeoutConfig {
+            push_seconds: Some(900),
+            ..Default::default()
+        }
+        .for_push();
+        assert_eq!(timeouts.request_timeout(), Some(Duration::from_secs(900)));
+
+        let timeouts = TimeoutConfig {
+            request_seconds: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(timeouts.request_timeout(), None);
+    }
+
+    #[test]
+    fn test_message_limits_default() {
+        let limits = MessageLimits::default();
+        assert_eq!(limits.max_encoding, 512 * 1024 * 1024);
+        assert_eq!(limits.max_decoding, 512 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_timeout_errors_are_distinct() {
+        assert!(is_timeout_status(&Status::deadline_exceeded("deadline")));
+        assert!(is_timeout_status(&Status::cancelled("Timeout expired")));
+        assert!(!is_timeout_status(&Status::unavailable("refused")));
+
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert!(!is_timeout(&refused));
+        let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert!(is_timeout(&timed_out));
+
+        let timeout = Some(Duration::from_secs(5));
+        let err = call_error(Status::deadline_exceeded("deadline"), "push", timeout);
+        assert_eq!(err.to_string(), "Timed out trying to push after 5s");
+        let err = call_error(Status::unavailable("refused"), "push", timeout);
+        assert_eq!(err.to_string(), "Failed to push");
+    }
+
+    #[test]
+    fn test_auth_interceptor_sets_header() {
+        let credential = Credential::Bearer {
+            token: AuthToken("secret".to_string()),
+        };
+        let mut interceptor = AuthInterceptor::new(Some(&credential)).unwrap();
+
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert_eq!(
+            request.metadata().get("authorization").unwrap(),
+            "Bearer secret"
+        );
+    }
+
+    #[test]
+    fn test_auth_token_redacted() {
+        let token = AuthToken("secret".to_string());
+        assert!(!format!("{:?}", token).contains("secret"));
+    }
+
+    #[test]
+    fn test_credentials_file_lookup() {
+        let file: CredentialsFile = toml::from_str(
+            r#"
+            ["registry.example.com"]
+            token = "host-token"
+
+            ["registry.example.com:50001"]
+            username = "ci"
+            password = "hunter2"
+            "#,
+        )
+        .unwrap();
+
+        let header = |url: &str| {
+            let uri: Uri = url.parse().unwrap();
+            file.lookup(&uri).map(Credential::header)
+        };
+        assert_eq!(
+            header("https://registry.example.com").as_deref(),
+            Some("Bearer host-token")
+        );
+        assert_eq!(
+            header("https://registry.example.com:50001").as_deref(),
+            Some("Basic Y2k6aHVudGVyMg==")
+        );
+        assert_eq!(header("https://other.example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn test_explicit_token_wins() {
+        let uri: Uri = "https://registry.example.com".parse().unwrap();
+        let token = AuthToken("explicit".to_string());
+
+        let credential = resolve_credential(&uri, Some(&token))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(credential.header(), "Bearer explicit");
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = CredentialsFile::load(&dir.path().join("credentials.toml"))
+            .await
+            .unwrap();
+        assert!(file.0.is_empty());
+    }
+}
@@ -1,42 +1,403 @@
 use std::collections::HashMap;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use crate::api::worker::worker_service_client::WorkerServiceClient;
-use crate::api::worker::{ExecuteRequest, execute_response};
+use crate::api::worker::{ExecuteRequest, ExecuteResponse, execute_response};
+use crate::command::push::{OutputFormat, expand_vars};
+use crate::command::transport::{self, AuthChannel, ConnectOptions, RawClient};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::Args;
 use tracing::{debug, error, info};
 
-pub async fn run(key: String, body: String, metadata: Vec<String>) -> Result<()> {
-    info!("Triggering action: '{}'", key);
-    debug!("Request body: {}", body);
+const DEFAULT_WORKER_URL: &str = "http://[::1]:50003";
 
-    // Connect to the worker service
-    let mut client = match WorkerServiceClient::connect("http://[::1]:50003").await {
-        Ok(c) => {
-            debug!("Connected to WorkerService");
-            c
+#[derive(Args, Debug)]
+pub struct TriggerArgs {
+    action: String,
+    /// Inline request body (conflicts with --body-file)
+    payload: Option<String>,
+    /// Metadata entries, the first taking the payload's place when the body comes from
+    /// --body-file or --env-file
+    #[arg(value_name = "KEY=VALUE")]
+    metadata: Vec<String>,
+    /// Read metadata from a TOML or .env style file, overridden by inline entries
+    #[arg(long, value_name = "PATH")]
+    metadata_file: Option<PathBuf>,
+    /// Read defaults from a .env file: META_<KEY> entries become metadata and BODY or
+    /// BODY_FILE the request body, all overridden by the other arguments
+    #[arg(long, value_name = "PATH")]
+    env_file: Option<PathBuf>,
+    /// Worker service URL (defaults to $NOCTI_WORKER_URL or http://[::1]:50003)
+    #[arg(long)]
+    worker_url: Option<String>,
+    /// Read the request body from a file, or from stdin when set to '-'
+    #[arg(long, value_name = "PATH")]
+    body_file: Option<PathBuf>,
+    /// Substitute `${NAME}` in the body with VALUE, falling back to the environment
+    #[arg(long = "var", value_name = "NAME=VALUE")]
+    vars: Vec<String>,
+    /// Treat the body as a template even without --var, filling it from the environment
+    #[arg(long)]
+    template: bool,
+    /// Exit with a non-zero status when the worker returns a problem
+    #[arg(long)]
+    strict: bool,
+    /// Print the response incrementally as the worker streams it
+    #[arg(long)]
+    stream: bool,
+    /// Write the raw response body to a file, or to stdout when set to '-'
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+    /// Abort the action after this many seconds (default: no deadline)
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+    /// Print the outcome as text, or as a JSON object with a base64 body or problem details
+    #[arg(long, value_enum, default_value = "text", conflicts_with = "stream")]
+    format: OutputFormat,
+}
+
+pub(super) fn resolve_worker_url(worker_url: Option<String>) -> String {
+    worker_url
+        .or_else(|| std::env::var("NOCTI_WORKER_URL").ok())
+        .unwrap_or_else(|| DEFAULT_WORKER_URL.to_string())
+}
+
+/// Check the worker URL is an http(s) URL with a host
+pub(super) fn validate_worker_url(worker_url: &str) -> Result<()> {
+    transport::validate_url(worker_url, "worker")
+}
+
+/// Resolve the request body from the inline payload or a body file
+fn read_body(payload: Option<String>, body_file: Option<&Path>) -> Result<Vec<u8>> {
+    match (payload, body_file) {
+        (Some(_), Some(_)) => bail!("Cannot use both an inline payload and --body-file"),
+        (Some(payload), None) => Ok(payload.into_bytes()),
+        (None, Some(path)) if path == Path::new("-") => {
+            let mut body = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut body)
+                .context("Failed to read request body from stdin")?;
+            Ok(body)
         }
-        Err(e) => {
-            error!("Failed to connect to WorkerService: {}", e);
-            return Err(e.into());
+        (None, Some(path)) => {
+            std::fs::read(path).with_context(|| format!("Failed to read body file: {:?}", path))
+        }
+        (None, None) => bail!("A request body is required: pass a payload or --body-file"),
+    }
+}
+
+/// Split an inline `KEY=VALUE` metadata entry
+fn parse_metadata_entry(entry: &str) -> Option<(String, String)> {
+    entry
+        .split_once('=')
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+}
+
+/// Move a `KEY=VALUE` payload to the front of the metadata when the body comes from
+/// elsewhere, as in `trigger act --body-file body.json TENANT=acme`
+fn payload_as_metadata(
+    payload: Option<String>,
+    metadata: &mut Vec<String>,
+    has_body_source: bool,
+) -> Option<String> {
+    match payload {
+        Some(entry) if has_body_source && is_metadata_entry(&entry) => {
+            metadata.insert(0, entry);
+            None
+        }
+        payload => payload,
+    }
+}
+
+/// Whether `entry` looks like `KEY=VALUE` with a plain key, rather than a request body
+fn is_metadata_entry(entry: &str) -> bool {
+    parse_metadata_entry(entry).is_some_and(|(key, _)| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    })
+}
+
+/// Read metadata from a `.toml` file or an `.env` style file of `KEY=VALUE` lines
+fn read_metadata_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read metadata file: {:?}", path))?;
+
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        parse_metadata_toml(&content)
+            .with_context(|| format!("Failed to parse metadata file: {:?}", path))
+    } else {
+        parse_metadata_env(&content)
+            .with_context(|| format!("Failed to parse metadata file: {:?}", path))
+    }
+}
+
+/// Parse a flat TOML table, converting scalar values to strings
+fn parse_metadata_toml(content: &str) -> Result<HashMap<String, String>> {
+    let table: toml::Table = toml::from_str(content).context("Invalid TOML")?;
+
+    table
+        .into_iter()
+        .map(|(key, value)| match value {
+            toml::Value::String(value) => Ok((key, value)),
+            toml::Value::Array(_) | toml::Value::Table(_) => {
+                bail!("Metadata value for '{}' must be a scalar", key)
+            }
+            value => Ok((key, value.to_string())),
+        })
+        .collect()
+}
+
+/// Parse `KEY=VALUE` lines, skipping blank lines and `#` comments
+fn parse_metadata_env(content: &str) -> Result<HashMap<String, String>> {
+    let mut metadata = HashMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = parse_metadata_entry(line)
+            .filter(|(key, _)| !key.trim().is_empty())
+            .ok_or_else(|| {
+                anyhow::format_err!("Invalid metadata entry on line {}: {}", index + 1, line)
+            })?;
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        metadata.insert(key.trim().to_string(), value.to_string());
+    }
+
+    Ok(metadata)
+}
+
+/// Merge the metadata file with inline entries, inline entries taking precedence
+fn collect_metadata(
+    inline: Vec<String>,
+    metadata_file: Option<&Path>,
+) -> Result<HashMap<String, String>> {
+    let mut metadata = match metadata_file {
+        Some(path) => read_metadata_file(path)?,
+        None => HashMap::new(),
+    };
+
+    for meta in inline {
+        let (key, value) = parse_metadata_entry(&meta)
+            .ok_or_else(|| anyhow::format_err!("Invalid metadata entry: {}", meta))?;
+        metadata.insert(key, value);
+    }
+
+    Ok(metadata)
+}
+
+/// Request defaults loaded with `--env-file`
+#[derive(Debug, Default)]
+struct EnvFile {
+    /// `META_<KEY>` entries with the prefix removed
+    metadata: HashMap<String, String>,
+    /// Inline `BODY`
+    body: Option<String>,
+    /// `BODY_FILE`, relative to the env file's directory
+    body_file: Option<PathBuf>,
+}
+
+impl EnvFile {
+    /// Read and parse the env file at `path`
+    fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read env file: {:?}", path))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+
+        Self::parse(&content, base_dir)
+            .with_context(|| format!("Failed to parse env file: {:?}", path))
+    }
+
+    /// Split `.env` entries into metadata and body, ignoring other keys
+    fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let mut env = Self::default();
+
+        for (key, value) in parse_metadata_env(content)? {
+            if let Some(name) = key.strip_prefix("META_") {
+                env.metadata.insert(name.to_string(), value);
+                continue;
+            }
+
+            match key.as_str() {
+                "BODY" => env.body = Some(value),
+                "BODY_FILE" => env.body_file = Some(base_dir.join(value)),
+                _ => debug!("Ignoring env file entry '{}'", key),
+            }
+        }
+
+        if env.body.is_some() && env.body_file.is_some() {
+            bail!("Only one of BODY and BODY_FILE can be set");
+        }
+
+        Ok(env)
+    }
+
+    /// Whether `BODY` or `BODY_FILE` is set
+    fn has_body(&self) -> bool {
+        self.body.is_some() || self.body_file.is_some()
+    }
+
+    /// The request body from `BODY` or `BODY_FILE`, if either is set
+    fn read_body(&self) -> Result<Option<Vec<u8>>> {
+        if let Some(ref body) = self.body {
+            return Ok(Some(body.clone().into_bytes()));
         }
+
+        self.body_file
+            .as_deref()
+            .map(|path| {
+                std::fs::read(path).with_context(|| format!("Failed to read BODY_FILE: {:?}", path))
+            })
+            .transpose()
+    }
+}
+
+/// Resolve the request body, a payload or `--body-file` winning over the env file body
+fn resolve_body(
+    payload: Option<String>,
+    body_file: Option<&Path>,
+    env: &EnvFile,
+) -> Result<Vec<u8>> {
+    let env_body = match (&payload, body_file) {
+        (None, None) => env.read_body()?,
+        _ => None,
     };
 
-    let metahash = metadata
+    match env_body {
+        Some(body) => Ok(body),
+        None => read_body(payload, body_file),
+    }
+}
+
+/// Parse `--var` entries into template variables
+fn parse_vars(entries: Vec<String>) -> Result<HashMap<String, String>> {
+    entries
         .into_iter()
-        .map(|meta| {
-            meta.split_once('=')
-                .map(|(k, v)| (k.to_owned(), v.to_owned()))
-                .ok_or_else(|| anyhow::format_err!("Invalid metadata entry: {}", meta))
+        .map(|entry| {
+            parse_metadata_entry(&entry)
+                .filter(|(name, _)| !name.is_empty())
+                .ok_or_else(|| anyhow::format_err!("Invalid --var entry: {}", entry))
         })
-        .collect::<Result<HashMap<_, _>, _>>();
+        .collect()
+}
+
+/// Fill the `${NAME}` placeholders of a body template from `lookup`, failing with
+/// every placeholder left unresolved
+fn render_body(body: Vec<u8>, lookup: impl Fn(&str) -> Option<String>) -> Result<Vec<u8>> {
+    let template = String::from_utf8(body).context("A templated request body must be UTF-8")?;
+    let rendered = expand_vars(
+        &template,
+        lookup,
+        "Body template references unresolved variables",
+    )?;
+
+    Ok(rendered.into_bytes())
+}
+
+pub async fn run(args: TriggerArgs, quiet: bool) -> Result<()> {
+    let TriggerArgs {
+        action: key,
+        payload,
+        metadata,
+        metadata_file,
+        env_file,
+        worker_url,
+        body_file,
+        vars,
+        template,
+        strict,
+        stream,
+        output,
+        timeout,
+        format,
+    } = args;
+
+    info!(action = %key, "Triggering action");
+    let env = match env_file {
+        Some(ref path) => EnvFile::read(path)?,
+        None => EnvFile::default(),
+    };
 
-    let request = tonic::Request::new(ExecuteRequest {
+    let mut metadata = metadata;
+    let has_body_source = body_file.is_some() || env.has_body();
+    let payload = payload_as_metadata(payload, &mut metadata, has_body_source);
+
+    let mut body = resolve_body(payload, body_file.as_deref(), &env)?;
+    if template || !vars.is_empty() {
+        let vars = parse_vars(vars)?;
+        body = render_body(body, |name| {
+            vars.get(name).cloned().or_else(|| std::env::var(name).ok())
+        })?;
+    }
+    debug!("Request body: {}", String::from_utf8_lossy(&body));
+
+    let mut all_metadata = env.metadata;
+    all_metadata.extend(collect_metadata(metadata, metadata_file.as_deref())?);
+    let metadata = all_metadata;
+
+    let worker_url = resolve_worker_url(worker_url);
+    validate_worker_url(&worker_url)?;
+
+    // Connect to the worker service
+    // Actions may legitimately run long, so only bound them when asked to
+    let mut connect_options = ConnectOptions::from_env();
+    connect_options.timeouts.request_seconds = Some(timeout.unwrap_or(0));
+    let request_timeout = connect_options.timeouts.request_timeout();
+    debug!(deadline = ?request_timeout, "Effective action deadline");
+    let channel = match transport::connect(&worker_url, &connect_options).await {
+        Ok(channel) => {
+            debug!(worker_url = %worker_url, "Connected to WorkerService");
+            channel
+        }
+        Err(e) => {
+            error!("Failed to connect to WorkerService: {:#}", e);
+            return Err(e);
+        }
+    };
+
+    let mut request = tonic::Request::new(ExecuteRequest {
         action: key.clone(),
-        body: body.into(),
-        metadata: metahash?,
+        body,
+        metadata,
     });
+    if let Some(timeout) = request_timeout {
+        request.set_timeout(timeout);
+    }
+
+    if stream {
+        return run_stream(
+            channel,
+            request,
+            &key,
+            strict,
+            output.as_deref(),
+            &connect_options,
+            quiet,
+        )
+        .await;
+    }
 
     info!("Sending ExecuteRequest to worker");
+    let limits = connect_options.limits;
+    let mut client = WorkerServiceClient::new(channel)
+        .max_encoding_message_size(limits.max_encoding)
+        .max_decoding_message_size(limits.max_decoding);
+    if let Some(encoding) = connect_options.compression.encoding() {
+        client = client.send_compressed(encoding).accept_compressed(encoding);
+    }
     let response = match client.execute(request).await {
         Ok(resp) => {
             debug!("Received response from worker");
@@ -44,22 +405,440 @@ pub async fn run(key: String, body: String, metadata: Vec<String>) -> Result<()>
         }
         Err(e) => {
             error!("Worker execute call failed: {}", e);
-            return Err(e.into());
+            return Err(transport::call_error(
+                e,
+                &format!("execute action '{}'", key),
+                request_timeout,
+            ));
+        }
+    };
+
+    let outcome = response
+        .into_inner()
+        .outcome
+        .context("Worker response did not contain an outcome")?;
+
+    handle_outcome(outcome, &key, strict, output.as_deref(), format, quiet)
+}
+
+/// Whether the body is printed as text rather than written as raw bytes
+fn prints_text(output: Option<&Path>) -> bool {
+    output.is_none() && std::io::stdout().is_terminal()
+}
+
+/// Open the destination for the raw response body: a file, or stdout for `-` or no path,
+/// discarded when quiet
+fn open_output(output: Option<&Path>, quiet: bool) -> Result<Box<dyn Write>> {
+    match output {
+        Some(path) if path != Path::new("-") => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create output file: {:?}", path))?;
+            Ok(Box::new(file))
+        }
+        _ if quiet => Ok(Box::new(std::io::sink())),
+        _ => Ok(Box::new(std::io::stdout())),
+    }
+}
+
+/// Write the success body, printing it as text only on a terminal without `--output`
+fn write_body(body: &[u8], output: Option<&Path>, quiet: bool) -> Result<()> {
+    if !quiet && prints_text(output) {
+        println!("{}", String::from_utf8_lossy(body));
+        return Ok(());
+    }
+
+    let mut writer = open_output(output, quiet)?;
+    writer
+        .write_all(body)
+        .context("Failed to write response body")?;
+    writer.flush().context("Failed to write response body")?;
+
+    Ok(())
+}
+
+/// Write a JSON document on its own line, to a file or stdout
+fn write_json(value: &serde_json::Value, output: Option<&Path>) -> Result<()> {
+    let mut writer = open_output(output, false)?;
+    writeln!(writer, "{}", value).context("Failed to write JSON output")?;
+    writer.flush().context("Failed to write JSON output")?;
+
+    Ok(())
+}
+
+/// JSON object for a successful outcome, with the body base64 encoded
+fn success_json(body: &[u8]) -> serde_json::Value {
+    serde_json::json!({ "body": BASE64.encode(body) })
+}
+
+/// RFC 7807 problem details object, with the extensions as additional members
+fn problem_json(
+    problem_type: &str,
+    detail: &str,
+    instance: &str,
+    extensions: impl IntoIterator<Item = (String, String)>,
+) -> serde_json::Value {
+    let mut object: serde_json::Map<String, serde_json::Value> = extensions
+        .into_iter()
+        .map(|(key, value)| (key, value.into()))
+        .collect();
+
+    // The standard members cannot be replaced by an extension of the same name
+    object.insert("type".to_string(), problem_type.into());
+    object.insert("detail".to_string(), detail.into());
+    object.insert("instance".to_string(), instance.into());
+
+    serde_json::Value::Object(object)
+}
+
+/// Print a worker outcome, failing on a problem when `strict` is set
+fn handle_outcome(
+    outcome: execute_response::Outcome,
+    key: &str,
+    strict: bool,
+    output: Option<&Path>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    match outcome {
+        execute_response::Outcome::Success(success) => match format {
+            OutputFormat::Text => write_body(&success.body, output, quiet)?,
+            OutputFormat::Json => write_json(&success_json(&success.body), output)?,
+        },
+        execute_response::Outcome::Problem(problem) => {
+            match format {
+                OutputFormat::Json => {
+                    let extensions = problem
+                        .extensions
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()));
+                    let value = problem_json(
+                        &problem.r#type,
+                        &problem.detail,
+                        &problem.instance,
+                        extensions,
+                    );
+                    write_json(&value, output)?;
+                }
+                OutputFormat::Text if !quiet => {
+                    println!("{}", problem.r#type);
+                    println!("{}", problem.detail);
+                    println!("{}", problem.instance);
+                    for set in &problem.extensions {
+                        println!("{} {}", set.0, set.1);
+                    }
+                }
+                OutputFormat::Text => {}
+            }
+
+            if strict {
+                bail!("Action '{}' returned a problem: {}", key, problem.r#type);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute the action over the server-streaming endpoint, printing each chunk as it arrives
+async fn run_stream(
+    channel: AuthChannel,
+    request: tonic::Request<ExecuteRequest>,
+    key: &str,
+    strict: bool,
+    output: Option<&Path>,
+    connect_options: &ConnectOptions,
+    quiet: bool,
+) -> Result<()> {
+    info!("Sending streaming ExecuteRequest to worker");
+    let request_timeout = connect_options.timeouts.request_timeout();
+    let mut stream = match execute_stream(channel, request, connect_options).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            error!("Worker execute stream call failed: {}", e);
+            return Err(transport::call_error(
+                e,
+                &format!("execute action '{}'", key),
+                request_timeout,
+            ));
         }
     };
 
-    let output = response.into_inner().outcome.unwrap();
+    let text = !quiet && prints_text(output);
+    let mut writer = open_output(output, quiet)?;
+    while let Some(response) = stream
+        .message()
+        .await
+        .map_err(|e| transport::call_error(e, "read from worker stream", request_timeout))?
+    {
+        let Some(outcome) = response.outcome else {
+            debug!("Skipping stream message without an outcome");
+            continue;
+        };
 
-    if let execute_response::Outcome::Success(success) = output {
-        println!("{}", String::from_utf8_lossy(&success.body));
-    } else if let execute_response::Outcome::Problem(problem) = output {
-        println!("{}", problem.r#type);
-        println!("{}", problem.detail);
-        println!("{}", problem.instance);
-        for set in problem.extensions {
-            println!("{} {}", set.0, set.1);
+        match outcome {
+            execute_response::Outcome::Success(chunk) => {
+                writer.write_all(&chunk.body)?;
+                writer.flush()?;
+            }
+            problem => {
+                if text {
+                    println!();
+                }
+                return handle_outcome(problem, key, strict, output, OutputFormat::Text, quiet);
+            }
         }
     }
 
+    debug!("Worker stream completed");
+    if text {
+        println!();
+    }
     Ok(())
 }
+
+/// Server-streaming call to `WorkerService/ExecuteStream`
+async fn execute_stream(
+    channel: AuthChannel,
+    request: tonic::Request<ExecuteRequest>,
+    connect_options: &ConnectOptions,
+) -> Result<tonic::Streaming<ExecuteResponse>, tonic::Status> {
+    RawClient::new(channel, connect_options)
+        .server_streaming::<ExecuteRequest, _>(
+            "/noctiforge.worker.WorkerService/ExecuteStream",
+            request,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_read_body_inline() {
+        let body = read_body(Some("{}".to_string()), None).unwrap();
+        assert_eq!(body, b"{}");
+    }
+
+    #[test]
+    fn test_read_body_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), [0xff, 0x00, 0x7b]).unwrap();
+
+        let body = read_body(None, Some(file.path())).unwrap();
+        assert_eq!(body, vec![0xff, 0x00, 0x7b]);
+    }
+
+    #[test]
+    fn test_read_body_conflict() {
+        let result = read_body(Some("{}".to_string()), Some(Path::new("body.json")));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_read_body_missing() {
+        assert!(read_body(None, None).is_err());
+    }
+
+    #[test]
+    fn test_write_body_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response.bin");
+
+        write_body(&[0x89, 0x50, 0x4e, 0x47, 0xff], Some(&path), false).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            vec![0x89, 0x50, 0x4e, 0x47, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_quiet_still_writes_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response.json");
+
+        write_body(b"{}", Some(&path), true).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_problem_json() {
+        let value = problem_json(
+            "https://example.com/out-of-stock",
+            "Item is out of stock",
+            "/orders/42",
+            [
+                ("sku".to_string(), "A-1".to_string()),
+                ("type".to_string(), "ignored".to_string()),
+            ],
+        );
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "type": "https://example.com/out-of-stock",
+                "detail": "Item is out of stock",
+                "instance": "/orders/42",
+                "sku": "A-1",
+            })
+        );
+    }
+
+    #[test]
+    fn test_success_json() {
+        assert_eq!(
+            success_json(b"hello"),
+            serde_json::json!({ "body": "aGVsbG8=" })
+        );
+    }
+
+    #[test]
+    fn test_parse_metadata_env() {
+        let metadata =
+            parse_metadata_env("# comment\n\nTENANT=acme\nexport REGION=\"eu-west\"\n").unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata["TENANT"], "acme");
+        assert_eq!(metadata["REGION"], "eu-west");
+    }
+
+    #[test]
+    fn test_parse_metadata_env_malformed() {
+        let err = parse_metadata_env("TENANT=acme\nREGION\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_parse_metadata_toml() {
+        let metadata = parse_metadata_toml("tenant = \"acme\"\nretries = 3\n").unwrap();
+        assert_eq!(metadata["tenant"], "acme");
+        assert_eq!(metadata["retries"], "3");
+
+        assert!(parse_metadata_toml("tags = [\"a\"]").is_err());
+    }
+
+    #[test]
+    fn test_inline_metadata_overrides_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.env");
+        std::fs::write(&path, "TENANT=acme\nREGION=eu-west\n").unwrap();
+
+        let metadata = collect_metadata(vec!["TENANT=other".to_string()], Some(&path)).unwrap();
+        assert_eq!(metadata["TENANT"], "other");
+        assert_eq!(metadata["REGION"], "eu-west");
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let env = EnvFile::parse(
+            "META_TENANT=acme\nMETA_REGION=eu-west\nBODY_FILE=body.json\nPATH=/usr/bin\n",
+            Path::new("/requests"),
+        )
+        .unwrap();
+
+        assert_eq!(env.metadata.len(), 2);
+        assert_eq!(env.metadata["TENANT"], "acme");
+        assert_eq!(env.metadata["REGION"], "eu-west");
+        assert_eq!(env.body, None);
+        assert_eq!(env.body_file, Some(PathBuf::from("/requests/body.json")));
+
+        assert!(EnvFile::parse("BODY={}\nBODY_FILE=body.json\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_env_file_body() {
+        let env = EnvFile::parse("BODY={\"id\": 1}\n", Path::new("")).unwrap();
+        assert_eq!(env.read_body().unwrap(), Some(b"{\"id\": 1}".to_vec()));
+
+        assert_eq!(EnvFile::default().read_body().unwrap(), None);
+    }
+
+    #[test]
+    fn test_payload_overrides_env_file_body() {
+        let env = EnvFile::parse("BODY=from-env\n", Path::new("")).unwrap();
+
+        let body = resolve_body(Some("from-cli".to_string()), None, &env).unwrap();
+        assert_eq!(body, b"from-cli");
+
+        let body = resolve_body(None, None, &env).unwrap();
+        assert_eq!(body, b"from-env");
+    }
+
+    fn parse_args(args: &[&str]) -> TriggerArgs {
+        let args = ["noctiForge", "trigger"].iter().chain(args).copied();
+        match super::super::Cli::try_parse_from(args).unwrap().command {
+            super::super::Command::Trigger(args) => args,
+            command => panic!("unexpected command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_payload_and_metadata_positionals() {
+        let args = parse_args(&["act", "{}", "A=1", "B=2", "--strict"]);
+        assert_eq!(args.payload.as_deref(), Some("{}"));
+        assert_eq!(args.metadata, vec!["A=1".to_string(), "B=2".to_string()]);
+        assert!(args.strict);
+
+        let mut metadata = args.metadata;
+        let payload = payload_as_metadata(args.payload, &mut metadata, false);
+        assert_eq!(payload.as_deref(), Some("{}"));
+        assert_eq!(metadata.len(), 2);
+    }
+
+    #[test]
+    fn test_metadata_with_body_file() {
+        let args = parse_args(&["act", "--body-file", "body.json", "TENANT=acme", "--strict"]);
+        assert_eq!(args.body_file, Some(PathBuf::from("body.json")));
+        assert!(args.strict);
+
+        let mut metadata = args.metadata;
+        let payload = payload_as_metadata(args.payload, &mut metadata, true);
+        assert_eq!(payload, None);
+        assert_eq!(metadata, vec!["TENANT=acme".to_string()]);
+
+        let mut metadata = Vec::new();
+        let payload =
+            payload_as_metadata(Some("{\"a\": \"b=c\"}".to_string()), &mut metadata, true);
+        assert!(payload.is_some());
+        assert!(metadata.is_empty());
+    }
+
+    #[test]
+    fn test_env_file_body_with_inline_metadata() {
+        let args = parse_args(&["act", "KEY=V", "--env-file", "req.env"]);
+        assert_eq!(args.env_file, Some(PathBuf::from("req.env")));
+
+        let env = EnvFile::parse("BODY=from-env\n", Path::new("")).unwrap();
+        let mut metadata = args.metadata;
+        let payload = payload_as_metadata(args.payload, &mut metadata, env.has_body());
+        let body = resolve_body(payload, args.body_file.as_deref(), &env).unwrap();
+        assert_eq!(body, b"from-env");
+
+        let metadata = collect_metadata(metadata, None).unwrap();
+        assert_eq!(metadata["KEY"], "V");
+    }
+
+    #[test]
+    fn test_parse_vars() {
+        let vars = parse_vars(vec!["ID=42".to_string(), "QUERY=a=b".to_string()]).unwrap();
+        assert_eq!(vars["ID"], "42");
+        assert_eq!(vars["QUERY"], "a=b");
+
+        assert!(parse_vars(vec!["ID".to_string()]).is_err());
+        assert!(parse_vars(vec!["=42".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_render_body() {
+        let vars = parse_vars(vec!["ID=42".to_string()]).unwrap();
+        let lookup = |name: &str| vars.get(name).cloned();
+
+        let body = render_body(br#"{"id": ${ID}, "shell": "$${HOME}"}"#.to_vec(), lookup).unwrap();
+        assert_eq!(body, br#"{"id": 42, "shell": "${HOME}"}"#);
+
+        let err = render_body(b"${ID} ${USER} ${TENANT}".to_vec(), lookup).unwrap_err();
+        assert!(err.to_string().ends_with("USER, TENANT"));
+
+        assert!(render_body(vec![0xff, 0xfe], lookup).is_err());
+    }
+}
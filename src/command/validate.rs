@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tracing::info;
+
+use crate::command::push;
+
+/// Load the project config and check the build resolves, without building it
+pub async fn run(path: &str, config_path: Option<&Path>, quiet: bool) -> Result<()> {
+    let project_path = Path::new(path);
+    info!("Validating project at: {:?}", project_path);
+
+    let mut config = push::load_config(project_path, config_path)?;
+    push::load_metadata(project_path, &mut config.build).await?;
+    let name = push::project_name(project_path, &config).await?;
+    let summary = push::validate_build(project_path, &config.build).await?;
+
+    if !quiet {
+        println!("{} is valid: {}", name, summary);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::push::CONFIG_FILE;
+
+    fn write_config(dir: &Path, build: &str) {
+        let content = format!("[project]\nname = \"handler\"\n\n[build]\n{}\n", build);
+        std::fs::write(dir.join(CONFIG_FILE), content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_custom_build() {
+        let dir = tempfile::tempdir().unwrap();
+        write_config(
+            dir.path(),
+            "type = \"custom\"\nscript = \"cp ./bootstrap $OUTPUT\"",
+        );
+
+        assert!(run(dir.path().to_str().unwrap(), None, false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_invalid_custom_build() {
+        let dir = tempfile::tempdir().unwrap();
+        write_config(dir.path(), "type = \"custom\"\nscript = \"\"");
+
+        assert!(
+            run(dir.path().to_str().unwrap(), None, false)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_build_requires_name() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE),
+            "[build]\ntype = \"custom\"\nscript = \"cp ./bootstrap $OUTPUT\"\n",
+        )
+        .unwrap();
+
+        let err = run(dir.path().to_str().unwrap(), None, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("[project] name is required"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_yaml_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Nocti.yaml"),
+            "project:\n  name: handler\nbuild:\n  type: custom\n  script: cp ./bootstrap $OUTPUT\n",
+        )
+        .unwrap();
+
+        assert!(run(dir.path().to_str().unwrap(), None, false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_ambiguous_config() {
+        let dir = tempfile::tempdir().unwrap();
+        write_config(
+            dir.path(),
+            "type = \"custom\"\nscript = \"cp ./bootstrap $OUTPUT\"",
+        );
+        std::fs::write(dir.path().join("Nocti.yml"), "build:\n  type: custom\n").unwrap();
+
+        let err = run(dir.path().to_str().unwrap(), None, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("multiple config files"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_missing_config() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(
+            run(dir.path().to_str().unwrap(), None, false)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use tonic::{Status, Streaming};
+
+use crate::command::transport::{AuthChannel, ConnectOptions, RawClient};
+
+/// Worker calls that are not part of the generated `WorkerServiceClient`
+pub struct WorkerClient {
+    inner: RawClient,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamLogsRequest {
+    /// Only stream lines of this action, every action when empty
+    #[prost(string, tag = "1")]
+    pub action: String,
+    /// Start with lines logged up to this many seconds ago, only new lines when 0
+    #[prost(uint64, tag = "2")]
+    pub since_seconds: u64,
+    /// Keep the stream open for new lines instead of ending after the backlog
+    #[prost(bool, tag = "3")]
+    pub follow: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LogEntry {
+    /// RFC 3339 time the line was logged
+    #[prost(string, tag = "1")]
+    pub timestamp: String,
+    #[prost(string, tag = "2")]
+    pub action: String,
+    #[prost(string, tag = "3")]
+    pub line: String,
+}
+
+impl WorkerClient {
+    pub fn new(channel: AuthChannel, options: &ConnectOptions) -> Self {
+        Self {
+            inner: RawClient::new(channel, options),
+        }
+    }
+
+    /// Open a server-streaming call to `WorkerService/StreamLogs`
+    pub async fn stream_logs(
+        &mut self,
+        request: StreamLogsRequest,
+    ) -> Result<Streaming<LogEntry>, Status> {
+        self.inner
+            .server_streaming("/noctiforge.worker.WorkerService/StreamLogs", request)
+            .await
+    }
+}